@@ -95,7 +95,7 @@ impl AABB {
         2
     }
 
-    fn pad_to_minimums(&mut self) -> () {
+    fn pad_to_minimums(&mut self) {
         let delta: f64 = 0.0001;
         if self.x.size() < delta {
             self.x = self.x.expand(delta);
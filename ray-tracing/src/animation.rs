@@ -0,0 +1,103 @@
+use crate::vec3::{Point3, Vec3};
+
+// Camera parameters at one point of the animation, time is in frames
+#[derive(Clone, Copy)]
+pub struct CameraKey {
+    pub time: f64,
+    pub look_from: Point3,
+    pub look_at: Point3,
+    pub vertical_view_angle: f64,
+    pub focus_distance: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interpolation {
+    Linear,
+    // Passes through every key with a continuous velocity
+    CatmullRom,
+}
+
+#[derive(Clone)]
+pub struct CameraAnimation {
+    keys: Vec<CameraKey>,
+    interpolation: Interpolation,
+}
+
+impl CameraAnimation {
+    pub fn new(interpolation: Interpolation) -> Self {
+        Self {
+            keys: Vec::new(),
+            interpolation,
+        }
+    }
+
+    // Keys may be added in any order
+    pub fn add_key(&mut self, key: CameraKey) {
+        let index: usize = self.keys.partition_point(|k| k.time <= key.time);
+        self.keys.insert(index, key);
+    }
+
+    pub fn keys(&self) -> &[CameraKey] {
+        &self.keys
+    }
+
+    // Held at the first and last key outside of their range
+    pub fn evaluate(&self, time: f64) -> Option<CameraKey> {
+        let first: &CameraKey = self.keys.first()?;
+        let last: &CameraKey = self.keys.last()?;
+        if time <= first.time {
+            return Some(CameraKey { time, ..*first })
+        }
+        if time >= last.time {
+            return Some(CameraKey { time, ..*last })
+        }
+        let next: usize = self.keys.partition_point(|k| k.time <= time);
+        let (k1, k2) = (&self.keys[next - 1], &self.keys[next]);
+        let t: f64 = (time - k1.time) / (k2.time - k1.time);
+        let key: CameraKey = match self.interpolation {
+            Interpolation::Linear => CameraKey {
+                time,
+                look_from: lerp(&k1.look_from, &k2.look_from, t),
+                look_at: lerp(&k1.look_at, &k2.look_at, t),
+                vertical_view_angle: k1.vertical_view_angle + t * (k2.vertical_view_angle - k1.vertical_view_angle),
+                focus_distance: k1.focus_distance + t * (k2.focus_distance - k1.focus_distance),
+            },
+            Interpolation::CatmullRom => {
+                // The end keys are repeated to give the outer segments their missing neighbour
+                let k0: &CameraKey = if next >= 2 { &self.keys[next - 2] } else { k1 };
+                let k3: &CameraKey = self.keys.get(next + 1).unwrap_or(k2);
+                CameraKey {
+                    time,
+                    look_from: catmull_rom_vec3(&k0.look_from, &k1.look_from, &k2.look_from, &k3.look_from, t),
+                    look_at: catmull_rom_vec3(&k0.look_at, &k1.look_at, &k2.look_at, &k3.look_at, t),
+                    vertical_view_angle: catmull_rom(
+                        k0.vertical_view_angle, k1.vertical_view_angle, k2.vertical_view_angle, k3.vertical_view_angle, t
+                    ),
+                    focus_distance: catmull_rom(
+                        k0.focus_distance, k1.focus_distance, k2.focus_distance, k3.focus_distance, t
+                    ),
+                }
+            },
+        };
+        Some(key)
+    }
+}
+
+fn lerp(a: &Vec3, b: &Vec3, t: f64) -> Vec3 {
+    (1.0 - t) * *a + t * *b
+}
+
+// Uniform Catmull-Rom segment between p1 and p2
+fn catmull_rom(p0: f64, p1: f64, p2: f64, p3: f64, t: f64) -> f64 {
+    let t2: f64 = t * t;
+    let t3: f64 = t2 * t;
+    0.5 * (2.0 * p1 + (p2 - p0) * t + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2 + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+fn catmull_rom_vec3(p0: &Vec3, p1: &Vec3, p2: &Vec3, p3: &Vec3, t: f64) -> Vec3 {
+    Vec3::new(
+        catmull_rom(p0.x(), p1.x(), p2.x(), p3.x(), t),
+        catmull_rom(p0.y(), p1.y(), p2.y(), p3.y(), t),
+        catmull_rom(p0.z(), p1.z(), p2.z(), p3.z(), t),
+    )
+}
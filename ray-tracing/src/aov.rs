@@ -0,0 +1,113 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::color::Color;
+use crate::vec3::{Point3, Vec3};
+
+// 0 is left for pixels where nothing was hit
+static NEXT_OBJECT_ID: AtomicU32 = AtomicU32::new(1);
+
+// Identifier for a new primitive, in creation order
+pub fn next_object_id() -> u32 {
+    NEXT_OBJECT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+// Auxiliary buffers written alongside the beauty image, all taken from the first hit of each camera ray
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Aov {
+    Depth,
+    Normal,
+    Albedo,
+    Position,
+    Uv,
+    ObjectId,
+}
+
+impl Aov {
+    pub const ALL: [Aov; 6] = [Aov::Depth, Aov::Normal, Aov::Albedo, Aov::Position, Aov::Uv, Aov::ObjectId];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::Depth => "depth",
+            Aov::Normal => "normal",
+            Aov::Albedo => "albedo",
+            Aov::Position => "position",
+            Aov::Uv => "uv",
+            Aov::ObjectId => "object_id",
+        }
+    }
+}
+
+// What one camera sample saw first, depth is measured along the viewing direction
+#[derive(Clone, Copy, Default)]
+pub struct AovSample {
+    pub depth: f64,
+    pub normal: Vec3,
+    pub albedo: Color,
+    pub position: Point3,
+    pub u: f64,
+    pub v: f64,
+    pub object_id: u32,
+}
+
+// Sums over the samples of a pixel that hit something, the object ID is the first one seen
+#[derive(Clone, Copy, Default)]
+pub struct AovPixel {
+    pub hits: i32,
+    pub depth: f64,
+    pub normal: Vec3,
+    pub albedo: Color,
+    pub position: Point3,
+    pub u: f64,
+    pub v: f64,
+    pub object_id: u32,
+}
+
+impl AovPixel {
+    pub fn add(&mut self, sample: &AovSample) {
+        if self.hits == 0 {
+            self.object_id = sample.object_id;
+        }
+        self.hits += 1;
+        self.depth += sample.depth;
+        self.normal += sample.normal;
+        self.albedo += sample.albedo;
+        self.position += sample.position;
+        self.u += sample.u;
+        self.v += sample.v;
+    }
+
+    pub fn merge(&mut self, other: &AovPixel) {
+        if self.hits == 0 {
+            self.object_id = other.object_id;
+        }
+        self.hits += other.hits;
+        self.depth += other.depth;
+        self.normal += other.normal;
+        self.albedo += other.albedo;
+        self.position += other.position;
+        self.u += other.u;
+        self.v += other.v;
+    }
+
+    // Average over the hits, pixels that only saw the background are black
+    pub fn value(&self, aov: Aov) -> Color {
+        if self.hits == 0 {
+            return Color::default()
+        }
+        let scale: f64 = 1.0 / self.hits as f64;
+        match aov {
+            Aov::Depth => {
+                let depth: f64 = self.depth * scale;
+                Color::new(depth, depth, depth)
+            },
+            Aov::Normal => self.normal * scale,
+            Aov::Albedo => self.albedo * scale,
+            Aov::Position => self.position * scale,
+            Aov::Uv => Color::new(self.u * scale, self.v * scale, 0.0),
+            Aov::ObjectId => {
+                let id: f64 = self.object_id as f64;
+                Color::new(id, id, id)
+            },
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use crate::rtw_image::RTWImage;
+use crate::utilities::{degrees_to_radians, PI};
+use crate::vec3::{sample_unit_disk, Vec3};
+
+// Shape of the lens opening, which out of focus highlights take on
+#[derive(Clone, Debug, PartialEq)]
+pub enum Aperture {
+    Disk,
+    // Regular polygon inscribed in the disk, rotation in degrees
+    Polygon { blades: i32, rotation: f64 },
+    Image(ApertureImage),
+}
+
+impl Aperture {
+    // Point on the aperture within the unit disk (or square for images), from two uniform numbers
+    pub fn sample(&self, u: f64, v: f64) -> Vec3 {
+        match self {
+            Aperture::Disk => sample_unit_disk(u, v),
+            Aperture::Polygon { blades, rotation } => {
+                if *blades < 3 {
+                    return sample_unit_disk(u, v)
+                }
+                // Pick one of the triangles fanning out from the centre, then a point inside it
+                let blades: f64 = *blades as f64;
+                let scaled: f64 = u * blades;
+                let blade: f64 = scaled.floor().min(blades - 1.0);
+                let u: f64 = scaled - blade;
+                let start: f64 = degrees_to_radians(*rotation) + 2.0 * PI * blade / blades;
+                let end: f64 = start + 2.0 * PI / blades;
+                let a = Vec3::new(start.cos(), start.sin(), 0.0);
+                let b = Vec3::new(end.cos(), end.sin(), 0.0);
+                let r: f64 = u.sqrt();
+                r * ((1.0 - v) * a + v * b)
+            },
+            Aperture::Image(image) => image.sample(u, v),
+        }
+    }
+
+    // Distance from the centre to the farthest point the shape can reach
+    pub fn radius(&self) -> f64 {
+        match self {
+            Aperture::Image(_) => 2.0_f64.sqrt(),
+            _ => 1.0,
+        }
+    }
+}
+
+// Grayscale aperture mask, brighter pixels let through more light
+#[derive(Clone, Debug, PartialEq)]
+pub struct ApertureImage {
+    width: usize,
+    height: usize,
+    // Cumulative distributions: over the rows, then over the columns of each row
+    row_cdf: Vec<f64>,
+    column_cdfs: Vec<f64>,
+}
+
+impl ApertureImage {
+    pub fn new(image: &RTWImage) -> Self {
+        let width = image.width().max(1) as usize;
+        let height = image.height().max(1) as usize;
+        let mut row_cdf: Vec<f64> = vec![0.0; height + 1];
+        let mut column_cdfs: Vec<f64> = vec![0.0; height * (width + 1)];
+        for j in 0..height {
+            let row: &mut [f64] = &mut column_cdfs[j * (width + 1)..(j + 1) * (width + 1)];
+            for i in 0..width {
+                let index: usize = image.pixel_data(i as i32, j as i32);
+                let intensity: f64 = if image.width() > 0 {
+                    (image[index] as f64 + image[index + 1] as f64 + image[index + 2] as f64) / (3.0 * 255.0)
+                } else {
+                    1.0
+                };
+                row[i + 1] = row[i] + intensity;
+            }
+            row_cdf[j + 1] = row_cdf[j] + row[width];
+            normalize(row);
+        }
+        normalize(&mut row_cdf);
+        Self {
+            width,
+            height,
+            row_cdf,
+            column_cdfs,
+        }
+    }
+
+    // The image covers [-1, 1] x [-1, 1] with its top row at y = 1
+    fn sample(&self, u: f64, v: f64) -> Vec3 {
+        let (row, v_offset) = sample_cdf(&self.row_cdf, u);
+        let (column, u_offset) = sample_cdf(&self.column_cdfs[row * (self.width + 1)..(row + 1) * (self.width + 1)], v);
+        let x: f64 = (column as f64 + u_offset) / self.width as f64;
+        let y: f64 = (row as f64 + v_offset) / self.height as f64;
+        Vec3::new(2.0 * x - 1.0, 1.0 - 2.0 * y, 0.0)
+    }
+}
+
+// An all black image falls back to a uniform distribution
+pub(crate) fn normalize(cdf: &mut [f64]) {
+    let total: f64 = cdf[cdf.len() - 1];
+    let count: usize = cdf.len() - 1;
+    for (index, value) in cdf.iter_mut().enumerate() {
+        *value = if total > 0.0 { *value / total } else { index as f64 / count as f64 };
+    }
+}
+
+// Bin holding `u` and how far into that bin it falls
+pub(crate) fn sample_cdf(cdf: &[f64], u: f64) -> (usize, f64) {
+    let bins: usize = cdf.len() - 1;
+    let bin: usize = cdf.partition_point(|value| *value <= u).clamp(1, bins) - 1;
+    let width: f64 = cdf[bin + 1] - cdf[bin];
+    let offset: f64 = if width > 0.0 { (u - cdf[bin]) / width } else { 0.5 };
+    (bin, offset.clamp(0.0, 1.0))
+}
@@ -17,8 +17,8 @@ pub struct BVHNode {
 impl BVHNode {
     pub fn new(objects: &mut Vec<Box<dyn Hittable>>, start: usize, end: usize) -> Self {
         let mut bbox = AABB::default();
-        for object_index in start..end {
-            bbox = AABB::new_from_children(&bbox, &objects[object_index].bounding_box())
+        for object in &objects[start..end] {
+            bbox = AABB::new_from_children(&bbox, &object.bounding_box())
         }
 
         let object_span: usize = end - start;
@@ -29,7 +29,7 @@ impl BVHNode {
             2 => right = objects[start + 1].clone(),
             _ => {
                 let axis: i32 = bbox.longest_axis();
-                objects[start..end].sort_by(|a, b| Self::box_compare(a, b, axis));
+                objects[start..end].sort_by(|a, b| Self::box_compare(a.as_ref(), b.as_ref(), axis));
                 let mid: usize = start + object_span / 2;
                 left = Box::new(Self::new(objects, start, mid));
                 right = Box::new(Self::new(objects, mid, end));
@@ -48,7 +48,7 @@ impl BVHNode {
         Self::new(&mut list.objects, 0, end)
     }
 
-    pub fn box_compare(a: &Box<dyn Hittable>, b: &Box<dyn Hittable>, axis_index: i32) -> Ordering {
+    pub fn box_compare(a: &dyn Hittable, b: &dyn Hittable, axis_index: i32) -> Ordering {
         let a_axis_interval = *a.bounding_box().axis_interval(axis_index);
         let b_axis_interval = *b.bounding_box().axis_interval(axis_index);
         a_axis_interval.min.total_cmp(&b_axis_interval.min)
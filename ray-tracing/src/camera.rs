@@ -1,152 +1,915 @@
 use indicatif::{ProgressBar, ProgressStyle};
-use std::fs::File;
-use std::io::{Write, Result};
+use std::fs::remove_file;
+use std::io::{Error, ErrorKind, Result};
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use crate::color::{write_color, Color};
+use crate::animation::{CameraAnimation, CameraKey};
+use crate::aov::AovSample;
+use crate::aperture::Aperture;
+use crate::checkpoint::{load_checkpoint, save_checkpoint, CheckpointHeader};
+use crate::color::{luminance, Color};
+use crate::display::DisplayTransform;
+use crate::environment::Environment;
+use crate::film::{Film, FilmPixel};
+use crate::filter::Filter;
+use crate::framebuffer::FrameBuffer;
 use crate::hittable::{HitRecord, Hittable};
+use crate::hittable_list::HittableList;
 use crate::interval::Interval;
+use crate::light::{Light, LightSample};
+use crate::material::ScatterRecord;
 use crate::ray::Ray;
-use crate::utilities::{degrees_to_radians, random_double, INFINITY};
-use crate::vec3::{cross, random_in_unit_disk, unit_vector, Point3, Vec3};
+use crate::sampler::{Sampler, SamplerKind};
+use crate::utilities::{degrees_to_radians, hash_seed, seed_random, INFINITY, PI};
+use crate::vec3::{cross, dot, unit_vector, Point3, Vec3};
+
+// Set from a signal handler to stop rendering and return what has been accumulated
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+// A second interrupt exits straight away, for when the first one is not noticed outside of rendering
+pub fn interrupt() {
+    if INTERRUPTED.swap(true, Ordering::Relaxed) {
+        std::process::exit(130);
+    }
+}
+
+// Keeps dark pixels from needing a vanishingly small absolute error
+const ADAPTIVE_LUMINANCE_FLOOR: f64 = 0.01;
+
+// Even a path that still carries all its light is ended now and then, so every path terminates
+const ROULETTE_MAX_SURVIVAL: f64 = 0.95;
+
+// Pixels along each side of the grid traced to fingerprint the scene for checkpoints
+const SCENE_PROBES: i32 = 16;
 
 pub struct Camera {
     pub aspect_ratio: f64,
     pub image_width: i32,
     pub samples_per_pixel: i32,
+    // Safety cap on the bounces of a path, which Russian roulette usually ends much earlier
     pub max_depth: i32,
+    // Bounces every path makes before Russian roulette may end it
+    pub roulette_depth: i32,
     pub background: Color,
+    // Replaces the background when set
+    pub environment: Option<Box<dyn Environment>>,
+    // Emitters sampled directly at every diffuse bounce, each must also be part of the world
+    pub lights: HittableList,
+    // How light samples and material samples that reach the same light are weighed against each other
+    pub mis_heuristic: MisHeuristic,
+    // Point, spot and directional lights, all of them are tested at every diffuse bounce
+    pub delta_lights: Vec<Box<dyn Light>>,
     pub vertical_view_angle: f64,
     pub look_from: Point3,
     pub look_at: Point3,
     pub view_up: Vec3,
     pub defocus_angle: f64,
     pub focus_distance: f64,
+    pub projection: Projection,
+    pub lens: Option<PhysicalLens>,
+    // Part of the frame the shutter is open for, 0 to 1 spans the whole frame
+    pub shutter_open: f64,
+    pub shutter_close: f64,
+    pub aperture: Aperture,
+    pub cat_eye: f64,
+    pub animation: Option<CameraAnimation>,
+    pub frame: i32,
+    pub tile_size: i32,
+    pub threads: usize,
+    pub seed: u64,
+    pub display_transform: DisplayTransform,
+    pub adaptive_threshold: f64,
+    pub min_samples_per_pixel: i32,
+    pub sampler: SamplerKind,
+    pub filter: Filter,
+    pub crop_window: Option<CropWindow>,
+    pub checkpoint_path: Option<String>,
+    pub checkpoint_interval: Duration,
+    // Keeps the checkpoint of a finished render, so a later run with more samples can carry on from it
+    pub keep_checkpoint: bool,
+    pub samples_per_pass: i32,
+    pub time_budget: Option<Duration>,
     image_height: i32,
-    pixel_samples_scale: f64,
-    center: Point3,
-    pixel00_loc: Point3,
-    pixel_delta_u: Vec3,
-    pixel_delta_v: Vec3,
-    u: Vec3,
-    v: Vec3,
-    w: Vec3,
-    defocus_disk_u: Vec3,
-    defocus_disk_v: Vec3,
+    view: View,
 }
 
 impl Camera {
-    pub fn render(&mut self, world: &dyn Hittable, image: &mut File) -> Result<()> {
+    // Renders the whole image, or only the crop window as a smaller image when one is set
+    pub fn render(&mut self, world: &dyn Hittable) -> FrameBuffer {
+        self.render_progressive(world, |_| {})
+    }
+
+    // Same as render, but hands the image accumulated so far to `on_pass` after every pass
+    pub fn render_progressive(&mut self, world: &dyn Hittable, mut on_pass: impl FnMut(&FrameBuffer))
+            -> FrameBuffer {
         self.initialize();
+        let region: Tile = self.crop_region();
+        let film: Film = self.render_region(world, &region, &mut |film: &Film| on_pass(&self.resolve(film)));
+        self.resolve(&film)
+    }
 
-        writeln!(image, "P3")?;
-        writeln!(image, "{} {}", self.image_width, self.image_height)?;
-        writeln!(image, "255")?;
+    // Renders each frame of the animation in turn, handing it to `on_frame` once it is done
+    pub fn render_sequence(&mut self, world: &dyn Hittable, frames: RangeInclusive<i32>,
+            mut on_frame: impl FnMut(i32, &FrameBuffer)) {
+        for frame in frames {
+            if INTERRUPTED.load(Ordering::Relaxed) {
+                break
+            }
+            self.frame = frame;
+            let image: FrameBuffer = self.render(world);
+            on_frame(frame, &image);
+        }
+    }
 
-        let bar = ProgressBar::new(self.image_height as u64);
-        bar.set_message("Rendering...");
+    // Re-renders the crop window(or the whole image) on top of a full size frame
+    pub fn render_into(&mut self, world: &dyn Hittable, frame: &mut FrameBuffer) -> Result<()> {
+        self.initialize();
+        if frame.width() != self.image_width || frame.height() != self.image_height {
+            return Err(Error::new(ErrorKind::InvalidInput, format!(
+                "Frame is {}x{} but the camera renders {}x{}",
+                frame.width(), frame.height(), self.image_width, self.image_height
+            )))
+        }
+        let region: Tile = self.crop_region();
+        let rendered: FrameBuffer = self.render_region(world, &region, &mut |_| {}).to_framebuffer();
+        frame.copy_from(&rendered, region.x0, region.y0);
+        Ok(())
+    }
+
+    fn resolve(&self, film: &Film) -> FrameBuffer {
+        let mut frame: FrameBuffer = film.to_framebuffer();
+        frame.set_display_transform(&self.display_transform);
+        frame
+    }
+
+    // Adds `samples_per_pass` samples to every pixel per pass until `samples_per_pixel` is reached,
+    // the time budget runs out or the render is interrupted
+    fn render_region(&self, world: &dyn Hittable, region: &Tile, on_pass: &mut dyn FnMut(&Film)) -> Film {
+        let tiles: Vec<Tile> = self.tiles(region);
+        let header: Option<CheckpointHeader> = self.checkpoint_path.as_ref().map(|_| self.checkpoint_header(world, region));
+        let mut tile_films: Vec<Option<Film>> = self.load_checkpoint(header.as_ref(), &tiles);
+        let pass_samples: i32 =
+            if self.samples_per_pass > 0 { self.samples_per_pass } else { self.samples_per_pixel.max(1) };
+        let pass_count: i32 = ((self.samples_per_pixel + pass_samples - 1) / pass_samples).max(0);
+        let deadline: Option<Instant> = self.time_budget.map(|budget| Instant::now() + budget);
+        let bar = ProgressBar::new(tiles.len() as u64 * pass_count as u64);
         bar.set_style(ProgressStyle::with_template("[{elapsed_precise}] [{bar:40.cyan/blue}] {pos:>7}/{len:7} {msg}")
             .unwrap()
             .progress_chars("#>-"));
-        for j in 0..(self.image_height) {
-            bar.inc(1);
-            for i in 0..(self.image_width) {
-                let mut pixel_color = Color::default();
-                for _ in 0..self.samples_per_pixel {
-                    let ray: Ray = self.get_ray(i, j);
-                    pixel_color += self.ray_color(&ray, self.max_depth, world);
+
+        let mut film: Film = self.merge_tiles(region, &tile_films);
+        let last_checkpoint: Mutex<Instant> = Mutex::new(Instant::now());
+        let mut target: i32 = 0;
+        for pass in 1..=pass_count {
+            if self.should_stop(deadline) {
+                break
+            }
+            target = (target + pass_samples).min(self.samples_per_pixel);
+            bar.set_message(format!("Pass {pass}/{pass_count} ({target} spp)"));
+
+            // Workers pull tiles from a shared counter until none are left or the render has to stop
+            let next_tile = AtomicUsize::new(0);
+            let finished: Mutex<Vec<(usize, Film)>> = Mutex::new(Vec::new());
+            thread::scope(|scope| {
+                for _ in 0..self.thread_count() {
+                    scope.spawn(|| {
+                        let mut sampler: Box<dyn Sampler> = self.sampler.create(self.samples_per_pixel, self.seed, self.frame);
+                        loop {
+                            let index: usize = next_tile.fetch_add(1, Ordering::Relaxed);
+                            if index >= tiles.len() || self.should_stop(deadline) {
+                                break
+                            }
+                            let tile_film: Film = self.render_tile(
+                                &tiles[index], world, sampler.as_mut(), tile_films[index].as_ref(), target, deadline,
+                            );
+                            finished.lock().unwrap().push((index, tile_film));
+                            bar.inc(1);
+                            // Whoever is due writes a copy of the finished tiles, the others carry on rendering
+                            if let Some(header) = &header
+                                    && let Ok(mut last) = last_checkpoint.try_lock()
+                                    && last.elapsed() >= self.checkpoint_interval {
+                                let snapshot: Vec<(usize, Film)> = finished.lock().unwrap().clone();
+                                self.save_checkpoint(header, &snapshot, &tile_films);
+                                *last = Instant::now();
+                            }
+                        }
+                    });
                 }
+            });
 
-                write_color(image, &(self.pixel_samples_scale * pixel_color))?;
+            // Tiles that were not reached this pass keep what they had after the previous one
+            for (index, tile_film) in finished.into_inner().unwrap() {
+                tile_films[index] = Some(tile_film);
+            }
+            film = self.merge_tiles(region, &tile_films);
+            on_pass(&film);
+        }
+        let complete: bool = target >= self.samples_per_pixel && !self.should_stop(deadline);
+        if let Some(header) = &header {
+            if complete && !self.keep_checkpoint {
+                self.remove_checkpoint();
+            } else {
+                self.save_checkpoint(header, &[], &tile_films);
             }
         }
 
-        bar.set_message("Rendering: Done.");
-        bar.finish();
-        Ok(())
+        if INTERRUPTED.load(Ordering::Relaxed) {
+            bar.abandon_with_message("Rendering: Interrupted.");
+        } else if target < self.samples_per_pixel {
+            bar.abandon_with_message(format!("Rendering: Out of time after {target} spp."));
+        } else {
+            bar.set_message("Rendering: Done.");
+            bar.finish();
+        }
+        film
+    }
+
+    // Merges in tile order, so overlapping filter footprints sum the same way on any thread count
+    fn merge_tiles(&self, region: &Tile, tile_films: &[Option<Film>]) -> Film {
+        let mut film = Film::new(region.x0, region.y0, region.x1 - region.x0, region.y1 - region.y0);
+        for tile_film in tile_films.iter().flatten() {
+            film.merge(tile_film);
+        }
+        film
     }
 
-    fn initialize(&mut self) -> () {
+    fn should_stop(&self, deadline: Option<Instant>) -> bool {
+        INTERRUPTED.load(Ordering::Relaxed) || deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    // Continues every pixel of the tile from the samples it already has in `previous`, up to `target`
+    fn render_tile(
+        &self,
+        tile: &Tile,
+        world: &dyn Hittable,
+        sampler: &mut dyn Sampler,
+        previous: Option<&Film>,
+        target: i32,
+        deadline: Option<Instant>,
+    ) -> Film {
+        let padding: i32 = self.filter_padding();
+        let mut film: Film = match previous {
+            Some(film) => film.clone(),
+            None => Film::new(
+                tile.x0 - padding,
+                tile.y0 - padding,
+                tile.x1 - tile.x0 + 2 * padding,
+                tile.y1 - tile.y0 + 2 * padding,
+            ),
+        };
+        for j in tile.y0..tile.y1 {
+            for i in tile.x0..tile.x1 {
+                let pixel_index = (j * self.image_width + i) as u64;
+                loop {
+                    let pixel: FilmPixel = film.pixel(i, j).copied().unwrap_or_default();
+                    if pixel.samples >= target || self.is_converged(&pixel) {
+                        break
+                    }
+                    if self.should_stop(deadline) {
+                        return film
+                    }
+                    // Each pixel sample owns its stream, so the image does not depend on scheduling
+                    seed_random(hash_seed(&[self.seed, self.frame as u64, pixel_index, pixel.samples as u64]));
+                    sampler.start_pixel_sample(i, j, pixel.samples);
+                    let offset: Vec3 = self.sample_square(sampler);
+                    let mut first_hit = AovSample::default();
+                    let sample_color: Color = match self.get_ray(i, j, &offset, sampler) {
+                        Some(ray) => self.ray_color(&ray, world, sampler, Some(&mut first_hit)),
+                        None => Color::default(),
+                    };
+                    film.add_sample(
+                        i as f64 + 0.5 + offset.x(),
+                        j as f64 + 0.5 + offset.y(),
+                        &sample_color,
+                        &self.filter,
+                    );
+                    film.record_sample(i, j, luminance(&sample_color));
+                    if first_hit.object_id != 0 {
+                        film.record_first_hit(i, j, &first_hit);
+                    }
+                }
+            }
+        }
+        film
+    }
+
+    // Pixels each tile film reaches past its tile, so samples near the edge land in the neighbouring pixels
+    fn filter_padding(&self) -> i32 {
+        (self.filter.radius() - 0.5).ceil() as i32
+    }
+
+    fn is_converged(&self, pixel: &FilmPixel) -> bool {
+        if self.adaptive_threshold <= 0.0 || pixel.samples < self.min_samples_per_pixel.max(2) {
+            return false
+        }
+        pixel.error() <= self.adaptive_threshold * (pixel.mean + ADAPTIVE_LUMINANCE_FLOOR)
+    }
+
+    fn checkpoint_header(&self, world: &dyn Hittable, region: &Tile) -> CheckpointHeader {
+        CheckpointHeader {
+            image_width: self.image_width,
+            image_height: self.image_height,
+            region: (region.x0, region.y0, region.x1, region.y1),
+            tile_size: self.tile_size,
+            seed: self.seed,
+            frame: self.frame,
+            strata: if self.sampler == SamplerKind::Stratified { self.samples_per_pixel.max(1) } else { 0 },
+            settings: self.settings_fingerprint(),
+            scene: self.scene_fingerprint(world),
+        }
+    }
+
+    // Everything besides the scene and the sample counts that changes what the tile films accumulate
+    fn settings_fingerprint(&self) -> u64 {
+        let options: String = format!(
+            "{:?} {:?} {:?} {:?} {:?} {:?}",
+            self.filter, self.sampler, self.projection, self.lens, self.aperture, self.mis_heuristic,
+        );
+        let mut values: Vec<u64> = options.bytes().map(u64::from).collect();
+        values.extend([self.max_depth, self.roulette_depth].map(|value| value as u64));
+        values.extend([self.defocus_angle, self.cat_eye, self.shutter_open, self.shutter_close].map(f64::to_bits));
+        hash_seed(&values)
+    }
+
+    // Traces the first sample of a sparse grid of pixels, so most edits to the objects, materials,
+    // lights or camera placement change the result
+    fn scene_fingerprint(&self, world: &dyn Hittable) -> u64 {
+        let bbox = world.bounding_box();
+        let mut values: Vec<u64> = [bbox.x.min, bbox.x.max, bbox.y.min, bbox.y.max, bbox.z.min, bbox.z.max]
+            .map(f64::to_bits)
+            .to_vec();
+        let mut sampler: Box<dyn Sampler> = self.sampler.create(self.samples_per_pixel, self.seed, self.frame);
+        for grid_j in 0..SCENE_PROBES {
+            for grid_i in 0..SCENE_PROBES {
+                let i: i32 = (2 * grid_i + 1) * self.image_width / (2 * SCENE_PROBES);
+                let j: i32 = (2 * grid_j + 1) * self.image_height / (2 * SCENE_PROBES);
+                seed_random(hash_seed(&[self.seed, self.frame as u64, (j * self.image_width + i) as u64, 0]));
+                sampler.start_pixel_sample(i, j, 0);
+                let offset: Vec3 = self.sample_square(sampler.as_mut());
+                let color: Color = match self.get_ray(i, j, &offset, sampler.as_mut()) {
+                    Some(ray) => self.ray_color(&ray, world, sampler.as_mut(), None),
+                    None => Color::default(),
+                };
+                values.extend([color.x(), color.y(), color.z()].map(f64::to_bits));
+            }
+        }
+        hash_seed(&values)
+    }
+
+    // Per tile films from a matching checkpoint, none when there is no usable one
+    fn load_checkpoint(&self, header: Option<&CheckpointHeader>, tiles: &[Tile]) -> Vec<Option<Film>> {
+        let mut previous: Vec<Option<Film>> = vec![None; tiles.len()];
+        let (Some(path), Some(header)) = (&self.checkpoint_path, header) else {
+            return previous
+        };
+        if !Path::new(path).exists() {
+            return previous
+        }
+        let padding: i32 = self.filter_padding();
+        let tile_sizes: Vec<(i32, i32)> = tiles.iter()
+            .map(|tile| (tile.x1 - tile.x0 + 2 * padding, tile.y1 - tile.y0 + 2 * padding))
+            .collect();
+        match load_checkpoint(path, header, &tile_sizes) {
+            Ok(films) => {
+                for (index, film) in films {
+                    previous[index] = Some(film);
+                }
+            },
+            Err(error) => eprintln!("Ignoring checkpoint {path}: {error}"),
+        }
+        previous
+    }
+
+    // Saves the finished tiles, plus the checkpointed ones that have not been picked up again yet
+    fn save_checkpoint(&self, header: &CheckpointHeader, finished: &[(usize, Film)], previous: &[Option<Film>]) {
+        let Some(path) = &self.checkpoint_path else {
+            return
+        };
+        let mut tiles: Vec<(usize, &Film)> = finished.iter().map(|(index, film)| (*index, film)).collect();
+        for (index, film) in previous.iter().enumerate() {
+            if let Some(film) = film && !finished.iter().any(|(i, _)| *i == index) {
+                tiles.push((index, film));
+            }
+        }
+        if let Err(error) = save_checkpoint(path, header, &tiles) {
+            eprintln!("Could not write checkpoint {path}: {error}");
+        }
+    }
+
+    fn remove_checkpoint(&self) {
+        let Some(path) = &self.checkpoint_path else {
+            return
+        };
+        if Path::new(path).exists() && let Err(error) = remove_file(path) {
+            eprintln!("Could not remove checkpoint {path}: {error}");
+        }
+    }
+
+    fn tiles(&self, region: &Tile) -> Vec<Tile> {
+        let size: i32 = self.tile_size.max(1);
+        let mut tiles: Vec<Tile> = Vec::new();
+        for y0 in (region.y0..region.y1).step_by(size as usize) {
+            for x0 in (region.x0..region.x1).step_by(size as usize) {
+                tiles.push(Tile {
+                    x0,
+                    y0,
+                    x1: (x0 + size).min(region.x1),
+                    y1: (y0 + size).min(region.y1),
+                });
+            }
+        }
+        tiles
+    }
+
+    // Pixel bounds of the crop window, clamped to the image
+    fn crop_region(&self) -> Tile {
+        let (x0, y0, x1, y1) = match self.crop_window {
+            None => (0, 0, self.image_width, self.image_height),
+            Some(CropWindow::Pixels { x0, y0, x1, y1 }) => (x0, y0, x1, y1),
+            Some(CropWindow::Normalized { x0, y0, x1, y1 }) => (
+                (x0 * self.image_width as f64).floor() as i32,
+                (y0 * self.image_height as f64).floor() as i32,
+                (x1 * self.image_width as f64).ceil() as i32,
+                (y1 * self.image_height as f64).ceil() as i32,
+            ),
+        };
+        let x0: i32 = x0.clamp(0, self.image_width);
+        let y0: i32 = y0.clamp(0, self.image_height);
+        Tile {
+            x0,
+            y0,
+            x1: x1.clamp(x0, self.image_width),
+            y1: y1.clamp(y0, self.image_height),
+        }
+    }
+
+    fn thread_count(&self) -> usize {
+        if self.threads > 0 {
+            return self.threads
+        }
+        thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+    }
+
+    fn initialize(&mut self) {
         self.image_height = ((self.image_width as f64) / self.aspect_ratio) as i32;
         self.image_height = if self.image_height < 1 { 1 } else { self.image_height };
-        self.pixel_samples_scale = 1.0 / (self.samples_per_pixel as f64);
-        self.center = self.look_from;
+        self.view = self.view_at(self.frame as f64);
+    }
+
+    // Where the camera is at `time` on the animation timeline, in frames, following the animation when there is one
+    fn view_at(&self, time: f64) -> View {
+        let static_key = CameraKey {
+            time: self.frame as f64,
+            look_from: self.look_from,
+            look_at: self.look_at,
+            vertical_view_angle: self.vertical_view_angle,
+            focus_distance: self.focus_distance,
+        };
+        let key: CameraKey = match &self.animation {
+            Some(animation) => animation.evaluate(time).unwrap_or(static_key),
+            None => static_key,
+        };
+        let center: Point3 = key.look_from;
+
+        // A physical lens replaces the view and defocus angles
+        let (theta, defocus_radius) = match &self.lens {
+            Some(lens) => (
+                lens.vertical_view_angle(self.image_width as f64 / self.image_height as f64),
+                lens.aperture_radius(),
+            ),
+            None => (
+                degrees_to_radians(key.vertical_view_angle),
+                key.focus_distance * degrees_to_radians(self.defocus_angle / 2.0).tan(),
+            ),
+        };
 
         // Viewport dimensions
-        let theta: f64 = degrees_to_radians(self.vertical_view_angle);
         let h: f64 = (theta / 2.0).tan();
-        let viewport_height: f64 = 2.0 * h * self.focus_distance;
-        let viewport_width: f64 = 
+        let viewport_height: f64 = 2.0 * h * key.focus_distance;
+        let viewport_width: f64 =
             viewport_height * ((self.image_width as f64) / (self.image_height as f64));
-        
+
         // Camera basis vectors
-        self.w = unit_vector(&(self.look_from - self.look_at));
-        self.u = unit_vector(&cross(&self.view_up, &self.w));
-        self.v = cross(&self.w, &self.u);
+        let w: Vec3 = unit_vector(&(key.look_from - key.look_at));
+        let u: Vec3 = unit_vector(&cross(&self.view_up, &w));
+        let v: Vec3 = cross(&w, &u);
 
         // Viewport edge vectors
-        let viewport_u: Vec3 = viewport_width * self.u;
-        let viewport_v: Vec3 = viewport_height * -self.v;
+        let viewport_u: Vec3 = viewport_width * u;
+        let viewport_v: Vec3 = viewport_height * -v;
 
         // Viewport pixel vectors
-        self.pixel_delta_u = viewport_u / (self.image_width as f64);
-        self.pixel_delta_v = viewport_v / (self.image_height as f64);
+        let pixel_delta_u: Vec3 = viewport_u / (self.image_width as f64);
+        let pixel_delta_v: Vec3 = viewport_v / (self.image_height as f64);
 
         // Pixel vector
-        let viewport_upper_left: Vec3 = 
-            self.center - self.focus_distance * self.w - viewport_u / 2.0 - viewport_v / 2.0;
-        self.pixel00_loc = 
-            viewport_upper_left + 0.5 * (self.pixel_delta_u + self.pixel_delta_v);
-
-        // Defocus disk basis vectors
-        let defocus_radius: f64 = self.focus_distance * degrees_to_radians(self.defocus_angle / 2.0).tan();
-        self.defocus_disk_u = self.u * defocus_radius;
-        self.defocus_disk_v = self.v * defocus_radius;
-    }
-
-    fn get_ray(&self, i: i32, j: i32) -> Ray {
-        let offset: Vec3 = self.sample_square();
-        let pixel_sample: Vec3 = 
-            self.pixel00_loc + ((i as f64 + offset.x()) * self.pixel_delta_u) 
-            + ((j as f64 + offset.y()) * self.pixel_delta_v);
-        let ray_origin: Point3 = 
-            if self.defocus_angle <= 0.0 {
-                self.center
+        let viewport_upper_left: Vec3 =
+            center - key.focus_distance * w - viewport_u / 2.0 - viewport_v / 2.0;
+        let pixel00_loc: Point3 =
+            viewport_upper_left + 0.5 * (pixel_delta_u + pixel_delta_v);
+
+        View {
+            center,
+            focus_distance: key.focus_distance,
+            pixel00_loc,
+            pixel_delta_u,
+            pixel_delta_v,
+            u,
+            v,
+            w,
+            // Defocus disk basis vectors
+            defocus_disk_u: u * defocus_radius,
+            defocus_disk_v: v * defocus_radius,
+        }
+    }
+
+    // None for the parts of a fisheye image outside its image circle, or when vignetting blocks the ray
+    fn get_ray(&self, i: i32, j: i32, offset: &Vec3, sampler: &mut dyn Sampler) -> Option<Ray> {
+        // Raster position mapped to [-1, 1] across the height, x to the right and y up
+        let x: f64 = (2.0 * (i as f64 + 0.5 + offset.x()) - self.image_width as f64) / self.image_height as f64;
+        let y: f64 = (self.image_height as f64 - 2.0 * (j as f64 + 0.5 + offset.y())) / self.image_height as f64;
+
+        // Direction in camera space (right, up, backwards) for the projections that fix one
+        let direction: Option<Vec3> = match self.projection {
+            Projection::Fisheye { field_of_view, mapping } => Some(self.fisheye_direction(x, y, field_of_view, mapping)?),
+            Projection::Equirectangular => {
+                let longitude: f64 = x * self.image_height as f64 / self.image_width as f64 * PI;
+                let latitude: f64 = y * PI / 2.0;
+                Some(Vec3::new(latitude.cos() * longitude.sin(), latitude.sin(), -latitude.cos() * longitude.cos()))
+            },
+            _ => None,
+        };
+        // Every equirectangular pixel looks out from the same point, so there is no lens to defocus with
+        let defocus: bool = self.projection != Projection::Equirectangular && !self.view.defocus_disk_u.near_zero();
+        let lens_point: Vec3 = if defocus { self.defocus_disk_sample(x, y, sampler)? } else { Vec3::default() };
+        // Rays are timed on the same timeline as the animation keys, the frame plus a point in the shutter
+        let ray_time: f64 =
+            self.frame as f64 + self.shutter_open + (self.shutter_close - self.shutter_open) * sampler.get_1d();
+
+        // A moving camera is placed where it was at the time of the ray
+        let view: View = if self.animation.is_some() { self.view_at(ray_time) } else { self.view };
+
+        // Where the lens sits and the point it brings into focus
+        let (lens_center, focus_point) = match (self.projection, direction) {
+            (Projection::Orthographic { height }, _) => {
+                let film_point: Point3 = view.center + (x * height / 2.0) * view.u + (y * height / 2.0) * view.v;
+                (film_point, film_point - view.focus_distance * view.w)
+            },
+            (_, Some(direction)) => {
+                let direction: Vec3 = direction.x() * view.u + direction.y() * view.v + direction.z() * view.w;
+                (view.center, view.center + view.focus_distance * direction)
+            },
+            _ => {
+                let pixel_sample: Point3 =
+                    view.pixel00_loc + ((i as f64 + offset.x()) * view.pixel_delta_u)
+                    + ((j as f64 + offset.y()) * view.pixel_delta_v);
+                (view.center, pixel_sample)
+            },
+        };
+        let ray_origin: Point3 = lens_center + lens_point.x() * view.defocus_disk_u + lens_point.y() * view.defocus_disk_v;
+        let ray_direction: Vec3 = focus_point - ray_origin;
+        Some(Ray::new(&ray_origin, &ray_direction, Some(ray_time)))
+    }
+
+    // Unit camera space direction for a point at distance r from the image centre, r = 1 at the top and bottom edges
+    fn fisheye_direction(&self, x: f64, y: f64, field_of_view: f64, mapping: FisheyeMapping) -> Option<Vec3> {
+        let r: f64 = (x * x + y * y).sqrt();
+        let theta_max: f64 = degrees_to_radians(field_of_view / 2.0);
+        let theta: f64 = match mapping {
+            FisheyeMapping::Equidistant => r * theta_max,
+            FisheyeMapping::Equisolid => {
+                let s: f64 = r * (theta_max / 2.0).sin();
+                if s > 1.0 {
+                    return None
+                }
+                2.0 * s.asin()
+            },
+            FisheyeMapping::Stereographic => 2.0 * (r * (theta_max / 2.0).tan()).atan(),
+            FisheyeMapping::Orthographic => {
+                let s: f64 = r * theta_max.min(PI / 2.0).sin();
+                if s > 1.0 {
+                    return None
+                }
+                s.asin()
+            },
+        };
+        if theta > PI {
+            return None
+        }
+        let (sin_phi, cos_phi) = if r > 0.0 { (y / r, x / r) } else { (0.0, 1.0) };
+        Some(Vec3::new(theta.sin() * cos_phi, theta.sin() * sin_phi, -theta.cos()))
+    }
+
+    fn sample_square(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        let (u, v) = sampler.get_2d();
+        Vec3::new(u - 0.5, v - 0.5, 0.0)
+    }
+
+    // Point on the unit aperture, None when the lens barrel blocks it
+    fn defocus_disk_sample(&self, x: f64, y: f64, sampler: &mut dyn Sampler) -> Option<Vec3> {
+        let (u, v) = sampler.get_2d();
+        let point: Point3 = self.aperture.sample(u, v);
+        // Off axis the barrel cuts the opening from the side facing the image edge, giving cat's eye bokeh
+        if self.cat_eye > 0.0 {
+            let barrel_center = Vec3::new(self.cat_eye * x, self.cat_eye * y, 0.0);
+            if (point - barrel_center).length() > self.aperture.radius() {
+                return None
+            }
+        }
+        Some(point)
+    }
+
+    // Follows one path from the camera, camera rays also pass `first_hit`, filled with what they hit
+    // for the AOVs. `throughput` is the fraction of light at the current vertex that reaches the camera.
+    fn ray_color(&self, ray: &Ray, world: &dyn Hittable, sampler: &mut dyn Sampler,
+            mut first_hit: Option<&mut AovSample>) -> Color {
+        let mut color = Color::default();
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut ray: Ray = ray.clone();
+        // Density the current ray was scattered with, when that bounce also sampled the lights
+        let mut scatter_pdf: Option<f64> = None;
+        for bounce in 0..self.max_depth {
+            let mut record = HitRecord::default();
+            if !world.hit(&ray, &Interval::new(0.001, INFINITY), &mut record) {
+                let color_from_background: Color = match &self.environment {
+                    Some(environment) => {
+                        // The environment sample at the previous bounce could have picked this direction too
+                        let mut value: Color = environment.value(ray.direction());
+                        if let Some(scatter_pdf) = scatter_pdf {
+                            value *= self.mis_heuristic.weight(scatter_pdf, environment.pdf_value(ray.direction()));
+                        }
+                        value
+                    },
+                    None => self.background,
+                };
+                color += throughput * color_from_background;
+                break
+            }
+            if let Some(first_hit) = first_hit.take() {
+                *first_hit = AovSample {
+                    depth: dot(&(record.point - self.view.center), &-self.view.w),
+                    normal: record.normal,
+                    albedo: record.material.albedo(&record),
+                    position: record.point,
+                    u: record.u,
+                    v: record.v,
+                    object_id: record.object_id,
+                };
+            }
+            let mut color_from_emission: Color = record.material.emmited(record.u, record.v, &record.point);
+            // The light sample at the previous bounce could have found this light too, only surfaces
+            // that emit anything are looked up among the lights
+            if let Some(scatter_pdf) = scatter_pdf
+                && color_from_emission.length_squared() > 0.0
+                && self.is_light(&ray, &record) {
+                let light_pdf: f64 = self.lights.pdf_value(ray.origin(), ray.direction(), ray.time());
+                color_from_emission *= self.mis_heuristic.weight(scatter_pdf, light_pdf);
+            }
+            color += throughput * color_from_emission;
+
+            let mut scatter_record = ScatterRecord::default();
+            if !record.material.scatter(&ray, &record, &mut scatter_record, sampler) {
+                break
+            }
+            let attenuation: Color = scatter_record.attenuation;
+            if scatter_record.specular {
+                throughput = throughput * attenuation;
+                scatter_pdf = None;
             } else {
-                self.defocus_disk_sample()
+                let sample_lights: bool = !self.lights.objects.is_empty();
+                if sample_lights {
+                    color += throughput * attenuation * self.sample_lights(&ray, &record, world, sampler);
+                }
+                if let Some(environment) = &self.environment {
+                    color += throughput * attenuation * self.sample_environment(environment.as_ref(), &ray, &record, world, sampler);
+                }
+                color += throughput * attenuation * self.sample_delta_lights(&ray, &record, world);
+                let scattering_pdf: f64 = record.material.scattering_pdf(&ray, &record, &scatter_record.scattered);
+                if scatter_record.pdf <= 0.0 || scattering_pdf <= 0.0 {
+                    break
+                }
+                throughput = scattering_pdf / scatter_record.pdf * throughput * attenuation;
+                scatter_pdf = if sample_lights || self.environment.is_some() { Some(scatter_record.pdf) } else { None };
+            }
+            ray = scatter_record.scattered;
+
+            // Russian roulette: paths that carry little light are ended at random, the survivors
+            // are boosted by the same odds so the estimate stays unbiased
+            if bounce + 1 >= self.roulette_depth {
+                let survival: f64 = throughput.x().max(throughput.y()).max(throughput.z()).min(ROULETTE_MAX_SURVIVAL);
+                if survival <= 0.0 || sampler.get_1d() >= survival {
+                    break
+                }
+                throughput /= survival;
+            }
+        }
+        color
+    }
+
+    // Light arriving from one point picked on the lights, through a shadow ray towards it, weighted
+    // by how much of it the material scatters back along the incoming ray. Materials are assumed to
+    // sample their own `scattering_pdf`, which is what a scattered ray could have reached it with.
+    fn sample_lights(&self, ray_in: &Ray, record: &HitRecord, world: &dyn Hittable, sampler: &mut dyn Sampler) -> Color {
+        let (u, v) = sampler.get_2d();
+        let direction: Vec3 = self.lights.sample_direction(&record.point, ray_in.time(), u, v);
+        let light_pdf: f64 = self.lights.pdf_value(&record.point, &direction, ray_in.time());
+        if light_pdf <= 0.0 {
+            return Color::default()
+        }
+        let shadow_ray = Ray::new(&record.point, &direction, Some(ray_in.time()));
+        let scattering_pdf: f64 = record.material.scattering_pdf(ray_in, record, &shadow_ray);
+        if scattering_pdf <= 0.0 {
+            return Color::default()
+        }
+        let mut light_record = HitRecord::default();
+        if !world.hit(&shadow_ray, &Interval::new(0.001, INFINITY), &mut light_record)
+            || !self.is_light(&shadow_ray, &light_record) {
+            return Color::default()
+        }
+        let emitted: Color = light_record.material.emmited(light_record.u, light_record.v, &light_record.point);
+        self.mis_heuristic.weight(light_pdf, scattering_pdf) * scattering_pdf / light_pdf * emitted
+    }
+
+    // Light from the direction the environment picks itself, such as towards the sun, when nothing
+    // blocks the way out, weighted against the material scattering that way
+    fn sample_environment(&self, environment: &dyn Environment, ray_in: &Ray, record: &HitRecord,
+            world: &dyn Hittable, sampler: &mut dyn Sampler) -> Color {
+        let (u, v) = sampler.get_2d();
+        let direction: Vec3 = environment.sample_direction(u, v);
+        let environment_pdf: f64 = environment.pdf_value(&direction);
+        if environment_pdf <= 0.0 {
+            return Color::default()
+        }
+        let shadow_ray = Ray::new(&record.point, &direction, Some(ray_in.time()));
+        let scattering_pdf: f64 = record.material.scattering_pdf(ray_in, record, &shadow_ray);
+        if scattering_pdf <= 0.0 {
+            return Color::default()
+        }
+        let mut blocker = HitRecord::default();
+        if world.hit(&shadow_ray, &Interval::new(0.001, INFINITY), &mut blocker) {
+            return Color::default()
+        }
+        self.mis_heuristic.weight(environment_pdf, scattering_pdf) * scattering_pdf / environment_pdf
+            * environment.value(&direction)
+    }
+
+    // Light from every delta light that the point can see, no material sample can find these
+    fn sample_delta_lights(&self, ray_in: &Ray, record: &HitRecord, world: &dyn Hittable) -> Color {
+        let mut color = Color::default();
+        for light in &self.delta_lights {
+            let Some(LightSample { direction, distance, irradiance }) = light.sample(&record.point) else {
+                continue
             };
-        let ray_direction: Vec3 = pixel_sample - ray_origin;
-        let ray_time: f64 = random_double(None);
-        Ray::new(&ray_origin, &ray_direction, Some(ray_time))
+            let shadow_ray = Ray::new(&record.point, &direction, Some(ray_in.time()));
+            let scattering_pdf: f64 = record.material.scattering_pdf(ray_in, record, &shadow_ray);
+            if scattering_pdf <= 0.0 {
+                continue
+            }
+            // Anything in between casts a shadow, stopping just short of the light itself
+            let mut blocker = HitRecord::default();
+            if world.hit(&shadow_ray, &Interval::new(0.001, distance * (1.0 - 1e-6)), &mut blocker) {
+                continue
+            }
+            color += scattering_pdf * irradiance;
+        }
+        color
+    }
+
+    // Whether the closest hit along `ray` is one of the sampled lights
+    fn is_light(&self, ray: &Ray, record: &HitRecord) -> bool {
+        let mut light_record = HitRecord::default();
+        record.object_id != 0
+            && self.lights.hit(ray, &Interval::new(0.001, INFINITY), &mut light_record)
+            && light_record.object_id == record.object_id
     }
+}
+
+// Camera frame and viewport at one instant, derived from the public parameters
+#[derive(Clone, Copy, Default)]
+struct View {
+    center: Point3,
+    focus_distance: f64,
+    pixel00_loc: Point3,
+    pixel_delta_u: Vec3,
+    pixel_delta_v: Vec3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    defocus_disk_u: Vec3,
+    defocus_disk_v: Vec3,
+}
+
+// Lens and sensor in photographic terms, lengths in millimetres
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PhysicalLens {
+    pub focal_length: f64,
+    pub sensor_width: f64,
+    pub sensor_height: f64,
+    pub f_number: f64,
+    // Scene units in one metre, to size the aperture
+    pub units_per_meter: f64,
+}
 
-    fn sample_square(&self) -> Vec3 {
-        Vec3::new(random_double(None) - 0.5, random_double(None) - 0.5, 0.0)
+impl PhysicalLens {
+    pub fn new(focal_length: f64, f_number: f64) -> Self {
+        Self {
+            focal_length,
+            f_number,
+            ..Self::default()
+        }
     }
 
-    fn defocus_disk_sample(&self) -> Point3 {
-        let point: Point3 = random_in_unit_disk();
-        self.center + point.x() * self.defocus_disk_u + point.y() * self.defocus_disk_v
+    // In radians, for the part of the sensor with the image's aspect ratio
+    fn vertical_view_angle(&self, aspect_ratio: f64) -> f64 {
+        let height: f64 = self.sensor_height.min(self.sensor_width / aspect_ratio);
+        2.0 * (height / (2.0 * self.focal_length)).atan()
     }
 
-    fn ray_color(&self, ray: &Ray, depth: i32, world: &dyn Hittable) -> Color {
-        if depth <= 0 {
-            return Color::default()
+    // In scene units, the entrance pupil is focal length / f-number across
+    fn aperture_radius(&self) -> f64 {
+        if self.f_number <= 0.0 {
+            return 0.0
         }
-        let mut record  = HitRecord::default();
-        if !world.hit(ray, &Interval::new(0.001, INFINITY), &mut record) {
-            return self.background
+        self.focal_length / (2.0 * self.f_number) / 1000.0 * self.units_per_meter
+    }
+}
+
+impl Default for PhysicalLens {
+    // A 50mm lens at f/8 on a full frame sensor
+    fn default() -> Self {
+        Self {
+            focal_length: 50.0,
+            sensor_width: 36.0,
+            sensor_height: 24.0,
+            f_number: 8.0,
+            units_per_meter: 1.0,
         }
-        let mut scattered = Ray::default();
-        let mut attenuation = Color::default();
-        let color_from_emission: Color = record.material.emmited(record.u, record.v, &record.point);
-        if !record.material.scatter(ray, &record, &mut attenuation, &mut scattered) {
-            return color_from_emission
+    }
+}
+
+// Multiple importance sampling weight for a sample drawn with density `pdf` that another strategy
+// could have drawn with `other_pdf`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MisHeuristic {
+    Balance,
+    // Balance on the squared densities, favouring whichever strategy is clearly better
+    Power,
+}
+
+impl MisHeuristic {
+    pub fn weight(&self, pdf: f64, other_pdf: f64) -> f64 {
+        let (a, b) = match self {
+            MisHeuristic::Balance => (pdf, other_pdf),
+            MisHeuristic::Power => (pdf * pdf, other_pdf * other_pdf),
+        };
+        if a + b <= 0.0 {
+            return 0.0
         }
-        let color_from_scatter: Color = attenuation * self.ray_color(&scattered, depth - 1, world);
-        color_from_emission + color_from_scatter
+        a / (a + b)
     }
 }
 
+// How the camera maps the image to rays, angles are in degrees
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    // Thin lens covering `vertical_view_angle`
+    Perspective,
+    // Parallel rays through a view `height` world units tall
+    Orthographic { height: f64 },
+    // `field_of_view` spans the image height, wider values fill the corners first
+    Fisheye { field_of_view: f64, mapping: FisheyeMapping },
+    // Full sphere, 360 degrees across the width and 180 across the height
+    Equirectangular,
+}
+
+// Relation between the angle off the optical axis and the distance from the image centre
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FisheyeMapping {
+    Equidistant,
+    Equisolid,
+    Stereographic,
+    Orthographic,
+}
+
+// Region of the image to render, the camera projection still covers the full frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CropWindow {
+    Pixels { x0: i32, y0: i32, x1: i32, y1: i32 },
+    Normalized { x0: f64, y0: f64, x1: f64, y1: f64 },
+}
+
+struct Tile {
+    x0: i32,
+    y0: i32,
+    x1: i32,
+    y1: i32,
+}
+
 impl Default for Camera {
     fn default() -> Self {
         Self { 
@@ -154,24 +917,42 @@ impl Default for Camera {
             image_width: 100,
             samples_per_pixel: 10,
             max_depth: 10,
+            roulette_depth: 3,
             background: Color::default(),
+            environment: None,
+            lights: HittableList::default(),
+            mis_heuristic: MisHeuristic::Power,
+            delta_lights: Vec::new(),
             vertical_view_angle: 90.0,
             look_from: Point3::default(),
             look_at: Point3::new(0.0, 0.0, -1.0),
             view_up: Vec3::new(0.0, 1.0, 0.0),
             defocus_angle: 0.0,
             focus_distance: 10.0,
+            projection: Projection::Perspective,
+            lens: None,
+            shutter_open: 0.0,
+            shutter_close: 1.0,
+            aperture: Aperture::Disk,
+            cat_eye: 0.0,
+            animation: None,
+            frame: 0,
+            tile_size: 16,
+            threads: 0,
+            seed: 0,
+            display_transform: DisplayTransform::default(),
+            adaptive_threshold: 0.0,
+            min_samples_per_pixel: 16,
+            sampler: SamplerKind::Independent,
+            filter: Filter::default(),
+            crop_window: None,
+            checkpoint_path: None,
+            checkpoint_interval: Duration::from_secs(60),
+            keep_checkpoint: true,
+            samples_per_pass: 16,
+            time_budget: None,
             image_height: 100,
-            pixel_samples_scale: 0.1,
-            center: Point3::default(),
-            pixel00_loc: Point3::default(),
-            pixel_delta_u: Point3::default(),
-            pixel_delta_v: Point3::default(),
-            u: Vec3::default(),
-            v: Vec3::default(),
-            w: Vec3::default(),
-            defocus_disk_u: Vec3::default(),
-            defocus_disk_v: Vec3::default(),
+            view: View::default(),
         }
     }    
 }
\ No newline at end of file
@@ -0,0 +1,104 @@
+use std::fs::{rename, File};
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Write};
+
+use crate::film::Film;
+
+const MAGIC: &[u8; 4] = b"RTCK";
+const VERSION: u32 = 5;
+
+// What a checkpoint must match to be resumed: same image, tiling, random streams, render settings and scene
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CheckpointHeader {
+    pub image_width: i32,
+    pub image_height: i32,
+    pub region: (i32, i32, i32, i32),
+    pub tile_size: i32,
+    pub seed: u64,
+    pub frame: i32,
+    // Strata per pixel of the stratified sampler, 0 for the samplers that do not depend on the sample count
+    pub strata: i32,
+    // Hash of the filter, sampler, path depths and lens, sample counts are left out so a render can be extended
+    pub settings: u64,
+    // Hash of what a few probe paths see of the scene
+    pub scene: u64,
+}
+
+// Writes the accumulated film of every started tile, replacing the file only once it is complete
+pub fn save_checkpoint(path: &str, header: &CheckpointHeader, tiles: &[(usize, &Film)]) -> Result<()> {
+    let temporary_path = format!("{path}.tmp");
+    {
+        let mut out = BufWriter::new(File::create(&temporary_path)?);
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        let (x0, y0, x1, y1) = header.region;
+        for value in [header.image_width, header.image_height, x0, y0, x1, y1, header.tile_size] {
+            out.write_all(&value.to_le_bytes())?;
+        }
+        out.write_all(&header.seed.to_le_bytes())?;
+        out.write_all(&header.frame.to_le_bytes())?;
+        out.write_all(&header.strata.to_le_bytes())?;
+        out.write_all(&header.settings.to_le_bytes())?;
+        out.write_all(&header.scene.to_le_bytes())?;
+        out.write_all(&(tiles.len() as u64).to_le_bytes())?;
+        for (index, film) in tiles {
+            out.write_all(&(*index as u64).to_le_bytes())?;
+            film.write(&mut out)?;
+        }
+        out.flush()?;
+    }
+    rename(&temporary_path, path)
+}
+
+// Reads the tile films of a checkpoint written with `expected`, `tile_sizes` holds the padded size of
+// each tile and bounds the films, so a stale or damaged file is turned down before anything large is read
+pub fn load_checkpoint(path: &str, expected: &CheckpointHeader, tile_sizes: &[(i32, i32)]) -> Result<Vec<(usize, Film)>> {
+    let mut input = BufReader::new(File::open(path)?);
+    let mut magic = [0u8; 4];
+    input.read_exact(&mut magic)?;
+    if &magic != MAGIC || read_u32(&mut input)? != VERSION {
+        return Err(Error::new(ErrorKind::InvalidData, format!("{path} is not a render checkpoint")))
+    }
+    let mut values = [0i32; 7];
+    for value in values.iter_mut() {
+        *value = read_u32(&mut input)? as i32;
+    }
+    let header = CheckpointHeader {
+        image_width: values[0],
+        image_height: values[1],
+        region: (values[2], values[3], values[4], values[5]),
+        tile_size: values[6],
+        seed: read_u64(&mut input)?,
+        frame: read_u32(&mut input)? as i32,
+        strata: read_u32(&mut input)? as i32,
+        settings: read_u64(&mut input)?,
+        scene: read_u64(&mut input)?,
+    };
+    if header != *expected {
+        return Err(Error::new(ErrorKind::InvalidData, "it was written for different render settings or a different scene"))
+    }
+    let tile_count: u64 = read_u64(&mut input)?;
+    if tile_count > tile_sizes.len() as u64 {
+        return Err(Error::new(ErrorKind::InvalidData, "it holds more tiles than the image has"))
+    }
+    let mut tiles: Vec<(usize, Film)> = Vec::new();
+    for _ in 0..tile_count {
+        let index = read_u64(&mut input)? as usize;
+        let Some((max_width, max_height)) = tile_sizes.get(index) else {
+            return Err(Error::new(ErrorKind::InvalidData, format!("it holds tile {index}, which the image does not have")))
+        };
+        tiles.push((index, Film::read(&mut input, *max_width, *max_height)?));
+    }
+    Ok(tiles)
+}
+
+fn read_u32(input: &mut impl Read) -> Result<u32> {
+    let mut bytes = [0u8; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(input: &mut impl Read) -> Result<u64> {
+    let mut bytes = [0u8; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
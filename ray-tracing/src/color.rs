@@ -1,6 +1,6 @@
-use std::fs::File;
 use std::io::{Write, Result};
 
+use crate::display::DisplayTransform;
 use crate::interval::Interval;
 use crate::vec3::Vec3;
 
@@ -13,17 +13,27 @@ pub fn linear_to_gamma(linear_component: f64) -> f64 {
     0.0
 }
 
-pub fn write_color(out: &mut File, pixel_color: &Color) -> Result<()> {
-    let r: f64 = linear_to_gamma(pixel_color.x());
-    let g: f64 = linear_to_gamma(pixel_color.y());
-    let b: f64 = linear_to_gamma(pixel_color.z());
+// Rec. 709 relative luminance
+pub fn luminance(color: &Color) -> f64 {
+    0.2126 * color.x() + 0.7152 * color.y() + 0.0722 * color.z()
+}
 
-    // Scale color components
+pub fn to_bytes(pixel_color: &Color, display: &DisplayTransform) -> [u8; 3] {
+    let display_color: Color = display.apply(pixel_color);
     let intensity = Interval::new(0.0, 0.999);
-    let rbyte: i32 = (256 as f64 * intensity.clamp(r)) as i32;
-    let gbyte: i32 = (256 as f64 * intensity.clamp(g)) as i32;
-    let bbyte: i32 = (256 as f64 * intensity.clamp(b)) as i32;
+    let scale = |c: f64| (256_f64 * intensity.clamp(c)) as u8;
+    [scale(display_color.x()), scale(display_color.y()), scale(display_color.z())]
+}
 
+pub fn to_words(pixel_color: &Color, display: &DisplayTransform) -> [u16; 3] {
+    let display_color: Color = display.apply(pixel_color);
+    let intensity = Interval::new(0.0, 0.99999);
+    let scale = |c: f64| (65536_f64 * intensity.clamp(c)) as u16;
+    [scale(display_color.x()), scale(display_color.y()), scale(display_color.z())]
+}
+
+pub fn write_color(out: &mut impl Write, pixel_color: &Color, display: &DisplayTransform) -> Result<()> {
+    let [rbyte, gbyte, bbyte] = to_bytes(pixel_color, display);
     writeln!(out, "{rbyte} {gbyte} {bbyte}")?;
     Ok(())
-}
\ No newline at end of file
+}
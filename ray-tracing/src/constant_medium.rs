@@ -1,4 +1,5 @@
 use crate::aabb::AABB;
+use crate::aov::next_object_id;
 use crate::color::Color;
 use crate::hittable::{HitRecord, Hittable};
 use crate::interval::{self, Interval};
@@ -12,7 +13,8 @@ use crate::vec3::Vec3;
 pub struct ConstantMedium {
     boundary: Box<dyn Hittable>,
     neg_inv_density: f64,
-    phase_function: Box<dyn Material>
+    phase_function: Box<dyn Material>,
+    id: u32,
 }
 
 impl ConstantMedium {
@@ -21,6 +23,7 @@ impl ConstantMedium {
             boundary,
             neg_inv_density: -1.0 / density,
             phase_function: Box::new(Isotropic::new(albedo)),
+            id: next_object_id(),
         }
     }
 
@@ -29,6 +32,7 @@ impl ConstantMedium {
             boundary,
             neg_inv_density: -1.0 / density,
             phase_function: Box::new(Isotropic::new_from_texture(texture)),
+            id: next_object_id(),
         }
     }
 }
@@ -66,6 +70,7 @@ impl Hittable for ConstantMedium {
         record.normal = Vec3::new(1.0, 0.0, 0.0);
         record.front_face = true;
         record.material = self.phase_function.clone();
+        record.object_id = self.id;
         true
     }
 
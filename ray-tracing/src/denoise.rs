@@ -0,0 +1,118 @@
+use crate::aov::Aov;
+use crate::color::Color;
+use crate::framebuffer::FrameBuffer;
+use crate::vec3::{unit_vector, Vec3};
+
+// Below this an albedo channel is treated as black and the colour is filtered as is
+const ALBEDO_EPSILON: f64 = 1e-3;
+
+// Joint bilateral filter: neighbours are averaged when their first hit looked alike (albedo, normal
+// and depth), so edges and texture survive while noise inside flat regions is smoothed out
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Denoiser {
+    pub radius: i32,
+    pub sigma_spatial: f64,
+    pub sigma_albedo: f64,
+    pub sigma_normal: f64,
+    pub sigma_depth: f64,
+    // 0 keeps the input, 1 is the fully filtered image
+    pub strength: f64,
+}
+
+impl Denoiser {
+    pub fn new(radius: i32, strength: f64) -> Self {
+        Self {
+            radius,
+            sigma_spatial: radius.max(1) as f64 / 2.0,
+            strength,
+            ..Self::default()
+        }
+    }
+
+    // Returns a copy of the frame with its beauty pixels denoised, the AOVs it was guided by are kept
+    pub fn denoise(&self, frame: &FrameBuffer) -> FrameBuffer {
+        let (width, height) = (frame.width(), frame.height());
+        let albedo: FrameBuffer = frame.aov(Aov::Albedo);
+        let normal: FrameBuffer = frame.aov(Aov::Normal);
+        let depth: FrameBuffer = frame.aov(Aov::Depth);
+
+        // Filter lighting without the surface colour, so texture detail is not blurred away
+        let mut irradiance: Vec<Color> = Vec::with_capacity((width * height) as usize);
+        let mut normals: Vec<Vec3> = Vec::with_capacity((width * height) as usize);
+        for j in 0..height {
+            for i in 0..width {
+                irradiance.push(demodulate(&frame.pixel(i, j), &albedo.pixel(i, j)));
+                let n: Vec3 = normal.pixel(i, j);
+                normals.push(if n.length_squared() > 0.0 { unit_vector(&n) } else { n });
+            }
+        }
+
+        let mut result: FrameBuffer = frame.clone();
+        let strength: f64 = self.strength.clamp(0.0, 1.0);
+        for j in 0..height {
+            for i in 0..width {
+                let index = (j * width + i) as usize;
+                let albedo_p: Color = albedo.pixel(i, j);
+                let depth_p: f64 = depth.pixel(i, j).x();
+                let mut sum = Color::default();
+                let mut weight_sum: f64 = 0.0;
+                for y in (j - self.radius).max(0)..=(j + self.radius).min(height - 1) {
+                    for x in (i - self.radius).max(0)..=(i + self.radius).min(width - 1) {
+                        let q = (y * width + x) as usize;
+                        let distance_squared = ((x - i) * (x - i) + (y - j) * (y - j)) as f64;
+                        let depth_q: f64 = depth.pixel(x, y).x();
+                        let relative_depth: f64 = (depth_p - depth_q) / (depth_p.max(depth_q) + 1e-6);
+                        let weight: f64 = gaussian(distance_squared, self.sigma_spatial)
+                            * gaussian((albedo_p - albedo.pixel(x, y)).length_squared(), self.sigma_albedo)
+                            * gaussian((normals[index] - normals[q]).length_squared(), self.sigma_normal)
+                            * gaussian(relative_depth * relative_depth, self.sigma_depth);
+                        sum += weight * irradiance[q];
+                        weight_sum += weight;
+                    }
+                }
+                // The centre pixel always has weight 1, so the sum is never empty
+                let filtered: Color = modulate(&(sum / weight_sum), &albedo_p);
+                let noisy: Color = frame.pixel(i, j);
+                result.set_pixel(i, j, &(noisy + strength * (filtered - noisy)));
+            }
+        }
+        result
+    }
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self {
+            radius: 6,
+            sigma_spatial: 3.0,
+            sigma_albedo: 0.1,
+            sigma_normal: 0.25,
+            sigma_depth: 0.02,
+            strength: 1.0,
+        }
+    }
+}
+
+fn gaussian(distance_squared: f64, sigma: f64) -> f64 {
+    (-distance_squared / (2.0 * sigma * sigma)).exp()
+}
+
+fn demodulate(color: &Color, albedo: &Color) -> Color {
+    let mut result: Color = *color;
+    for c in 0..3 {
+        if albedo[c] > ALBEDO_EPSILON {
+            result[c] /= albedo[c];
+        }
+    }
+    result
+}
+
+fn modulate(irradiance: &Color, albedo: &Color) -> Color {
+    let mut result: Color = *irradiance;
+    for c in 0..3 {
+        if albedo[c] > ALBEDO_EPSILON {
+            result[c] *= albedo[c];
+        }
+    }
+    result
+}
@@ -0,0 +1,125 @@
+use crate::color::{linear_to_gamma, Color};
+use crate::interval::Interval;
+use crate::vec3::{dot, Vec3};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ToneMapping {
+    Clamp,
+    Reinhard,
+    ReinhardExtended { white_point: f64 },
+    Filmic,
+    AcesFitted,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransferFunction {
+    Linear,
+    Gamma2,
+    Srgb,
+}
+
+// Maps linear scene radiance to display values in [0, 1]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DisplayTransform {
+    pub exposure: f64,
+    pub tone_mapping: ToneMapping,
+    pub transfer: TransferFunction,
+}
+
+impl DisplayTransform {
+    pub fn new(exposure: f64, tone_mapping: ToneMapping, transfer: TransferFunction) -> Self {
+        Self {
+            exposure,
+            tone_mapping,
+            transfer,
+        }
+    }
+
+    pub fn apply(&self, linear: &Color) -> Color {
+        let exposed: Color = 2.0_f64.powf(self.exposure) * *linear;
+        let mapped: Color = self.tone_map(&exposed);
+        let unit = Interval::new(0.0, 1.0);
+        Color::new(
+            unit.clamp(self.encode(mapped.x())),
+            unit.clamp(self.encode(mapped.y())),
+            unit.clamp(self.encode(mapped.z())),
+        )
+    }
+
+    fn tone_map(&self, color: &Color) -> Color {
+        match self.tone_mapping {
+            ToneMapping::Clamp => *color,
+            ToneMapping::Reinhard => map_components(color, |c| c / (1.0 + c)),
+            ToneMapping::ReinhardExtended { white_point } => {
+                let white_sq: f64 = white_point * white_point;
+                map_components(color, |c| c * (1.0 + c / white_sq) / (1.0 + c))
+            },
+            ToneMapping::Filmic => {
+                // Hable's curve, normalized so the white point maps to 1
+                let white_scale: f64 = 1.0 / hable(11.2);
+                map_components(&(2.0 * *color), |c| hable(c) * white_scale)
+            },
+            ToneMapping::AcesFitted => aces_fitted(color),
+        }
+    }
+
+    fn encode(&self, component: f64) -> f64 {
+        match self.transfer {
+            TransferFunction::Linear => component,
+            TransferFunction::Gamma2 => linear_to_gamma(component),
+            TransferFunction::Srgb => {
+                if component <= 0.0031308 {
+                    return 12.92 * component.max(0.0)
+                }
+                1.055 * component.powf(1.0 / 2.4) - 0.055
+            },
+        }
+    }
+}
+
+impl Default for DisplayTransform {
+    fn default() -> Self {
+        Self {
+            exposure: 0.0,
+            tone_mapping: ToneMapping::Clamp,
+            transfer: TransferFunction::Gamma2,
+        }
+    }
+}
+
+fn map_components(color: &Color, f: impl Fn(f64) -> f64) -> Color {
+    Color::new(f(color.x().max(0.0)), f(color.y().max(0.0)), f(color.z().max(0.0)))
+}
+
+fn hable(x: f64) -> f64 {
+    let (a, b, c, d, e, f) = (0.15, 0.50, 0.10, 0.20, 0.02, 0.30);
+    ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f
+}
+
+// Stephen Hill's fit of the ACES RRT and sRGB ODT
+fn aces_fitted(color: &Color) -> Color {
+    let input = [
+        Vec3::new(0.59719, 0.35458, 0.04823),
+        Vec3::new(0.07600, 0.90834, 0.01566),
+        Vec3::new(0.02840, 0.13383, 0.83777),
+    ];
+    let output = [
+        Vec3::new(1.60475, -0.53108, -0.07367),
+        Vec3::new(-0.10208, 1.10813, -0.00605),
+        Vec3::new(-0.00327, -0.07276, 1.07602),
+    ];
+    let color: Color = map_components(color, |c| c);
+    let v: Color = multiply(&input, &color);
+    let fitted: Color = map_components(&v, |c| {
+        (c * (c + 0.0245786) - 0.000090537) / (c * (0.983729 * c + 0.4329510) + 0.238081)
+    });
+    multiply(&output, &fitted)
+}
+
+fn multiply(rows: &[Vec3; 3], v: &Vec3) -> Vec3 {
+    Vec3::new(
+        dot(&rows[0], v),
+        dot(&rows[1], v),
+        dot(&rows[2], v),
+    )
+}
@@ -0,0 +1,148 @@
+use crate::aperture::{normalize, sample_cdf};
+use crate::color::{luminance, Color};
+use crate::rtw_image::RTWImage;
+use crate::utilities::{degrees_to_radians, PI};
+use crate::vec3::{unit_vector, Vec3};
+
+// Light arriving from infinitely far away, seen by rays that miss the world
+pub trait Environment: EnvironmentClone + Send + Sync {
+    fn value(&self, direction: &Vec3) -> Color;
+
+    // Density over solid angle of `sample_direction` giving `direction`, 0 for environments not worth sampling
+    fn pdf_value(&self, _direction: &Vec3) -> f64 {
+        0.0
+    }
+
+    // Direction towards a small bright part that rays seldom find by chance, from two uniform numbers
+    fn sample_direction(&self, _u: f64, _v: f64) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
+}
+
+pub trait EnvironmentClone {
+    fn clone_box(&self) -> Box<dyn Environment>;
+}
+
+impl<T> EnvironmentClone for T where T: 'static + Environment + Clone, {
+    fn clone_box(&self) -> Box<dyn Environment> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn Environment> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+// Equirectangular (latitude-longitude) image wrapped around the scene, laid out like the sphere's uv,
+// sampled in proportion to the light each pixel sends
+#[derive(Clone)]
+pub struct EnvironmentMap {
+    image: RTWImage,
+    // Cumulative distributions of luminance times the solid angle of each pixel: over the rows, then
+    // over the columns of each row. The luminance is the brightest of the pixel and its neighbours,
+    // which bounds what the bilinear lookup can return inside it.
+    row_cdf: Vec<f64>,
+    column_cdfs: Vec<f64>,
+    // Degrees about the y axis
+    pub rotation: f64,
+    pub intensity: f64,
+}
+
+impl EnvironmentMap {
+    pub fn new(image_filename: &str, rotation: f64, intensity: f64) -> Self {
+        Self::new_from_image(RTWImage::new(image_filename), rotation, intensity)
+    }
+
+    pub fn new_from_image(image: RTWImage, rotation: f64, intensity: f64) -> Self {
+        let width = image.width().max(0) as usize;
+        let height = image.height().max(0) as usize;
+        let mut row_cdf: Vec<f64> = vec![0.0; height + 1];
+        let mut column_cdfs: Vec<f64> = vec![0.0; height * (width + 1)];
+        for j in 0..height {
+            // Rows near the poles cover less of the sphere
+            let sin_theta: f64 = (PI * (j as f64 + 0.5) / height as f64).sin();
+            let row: &mut [f64] = &mut column_cdfs[j * (width + 1)..(j + 1) * (width + 1)];
+            for i in 0..width {
+                let mut brightest: f64 = 0.0;
+                for (di, dj) in (-1..=1).flat_map(|di| (-1..=1).map(move |dj| (di, dj))) {
+                    let x: i32 = (i as i32 + di).rem_euclid(width as i32);
+                    let [r, g, b] = image.pixel_float(x, j as i32 + dj);
+                    brightest = brightest.max(luminance(&Color::new(r as f64, g as f64, b as f64)));
+                }
+                row[i + 1] = row[i] + brightest * sin_theta;
+            }
+            row_cdf[j + 1] = row_cdf[j] + row[width];
+            normalize(row);
+        }
+        normalize(&mut row_cdf);
+        Self {
+            image,
+            row_cdf,
+            column_cdfs,
+            rotation,
+            intensity,
+        }
+    }
+
+    // Position in the image, u across and v down, both in [0, 1)
+    fn direction_to_uv(&self, direction: &Vec3) -> (f64, f64) {
+        let direction: Vec3 = unit_vector(direction);
+        let theta: f64 = (-direction.y()).clamp(-1.0, 1.0).acos();
+        let phi: f64 = (-direction.z()).atan2(direction.x()) + PI - degrees_to_radians(self.rotation);
+        ((phi / (2.0 * PI)).rem_euclid(1.0), (1.0 - theta / PI).clamp(0.0, 1.0))
+    }
+
+    fn pixel(&self, i: i32, j: i32) -> Color {
+        let [r, g, b] = self.image.pixel_float(i.rem_euclid(self.image.width()), j);
+        Color::new(r as f64, g as f64, b as f64)
+    }
+}
+
+impl Environment for EnvironmentMap {
+    fn value(&self, direction: &Vec3) -> Color {
+        if self.image.height() <= 0 {
+            return Color::new(1.0, 0.0, 1.0)
+        }
+        // Bilinear between the four nearest pixel centres, wrapping around horizontally
+        let (u, v) = self.direction_to_uv(direction);
+        let x: f64 = u * self.image.width() as f64 - 0.5;
+        let y: f64 = v * self.image.height() as f64 - 0.5;
+        let (i, j) = (x.floor() as i32, y.floor() as i32);
+        let (fx, fy) = (x - x.floor(), y - y.floor());
+        let top: Color = (1.0 - fx) * self.pixel(i, j) + fx * self.pixel(i + 1, j);
+        let bottom: Color = (1.0 - fx) * self.pixel(i, j + 1) + fx * self.pixel(i + 1, j + 1);
+        self.intensity * ((1.0 - fy) * top + fy * bottom)
+    }
+
+    fn pdf_value(&self, direction: &Vec3) -> f64 {
+        let (width, height) = (self.image.width(), self.image.height());
+        if width <= 0 || height <= 0 {
+            return 0.0
+        }
+        let (u, v) = self.direction_to_uv(direction);
+        let i = ((u * width as f64) as usize).min(width as usize - 1);
+        let j = ((v * height as f64) as usize).min(height as usize - 1);
+        let columns: &[f64] = &self.column_cdfs[j * (width as usize + 1)..];
+        let probability: f64 = (self.row_cdf[j + 1] - self.row_cdf[j]) * (columns[i + 1] - columns[i]);
+        let sin_theta: f64 = (PI * v).sin();
+        if sin_theta <= 0.0 {
+            return 0.0
+        }
+        // Each pixel spans 2 pi / width by pi / height in longitude and latitude
+        probability * (width * height) as f64 / (2.0 * PI * PI * sin_theta)
+    }
+
+    fn sample_direction(&self, u: f64, v: f64) -> Vec3 {
+        let (width, height) = (self.image.width() as usize, self.image.height() as usize);
+        if width == 0 || height == 0 {
+            return Vec3::new(1.0, 0.0, 0.0)
+        }
+        let (row, v_offset) = sample_cdf(&self.row_cdf, u);
+        let (column, u_offset) = sample_cdf(&self.column_cdfs[row * (width + 1)..(row + 1) * (width + 1)], v);
+        let theta: f64 = PI * (1.0 - (row as f64 + v_offset) / height as f64);
+        let phi: f64 = 2.0 * PI * (column as f64 + u_offset) / width as f64 - PI + degrees_to_radians(self.rotation);
+        Vec3::new(theta.sin() * phi.cos(), -theta.cos(), -theta.sin() * phi.sin())
+    }
+}
@@ -0,0 +1,226 @@
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+use crate::aov::{AovPixel, AovSample};
+use crate::color::Color;
+use crate::filter::Filter;
+use crate::framebuffer::FrameBuffer;
+use crate::utilities::INFINITY;
+use crate::vec3::{Point3, Vec3};
+
+// Filtered radiance sums, plus Welford's running statistics of the luminance of the
+// samples taken for this pixel (splats from neighbours only touch the sums)
+#[derive(Clone, Copy, Default)]
+pub struct FilmPixel {
+    pub weighted_sum: Color,
+    pub weight_sum: f64,
+    pub samples: i32,
+    pub mean: f64,
+    pub m2: f64,
+    pub aov: AovPixel,
+}
+
+impl FilmPixel {
+    // Half width of the 95% confidence interval of the mean luminance
+    pub fn error(&self) -> f64 {
+        if self.samples < 2 {
+            return INFINITY
+        }
+        let variance: f64 = self.m2 / (self.samples - 1) as f64;
+        1.96 * (variance / self.samples as f64).sqrt()
+    }
+
+    fn add_luminance(&mut self, value: f64) {
+        self.samples += 1;
+        let delta: f64 = value - self.mean;
+        self.mean += delta / self.samples as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    // Chan et al.'s pairwise update of the running statistics
+    fn merge(&mut self, other: &FilmPixel) {
+        self.weighted_sum += other.weighted_sum;
+        self.weight_sum += other.weight_sum;
+        self.aov.merge(&other.aov);
+        if other.samples == 0 {
+            return
+        }
+        let samples: i32 = self.samples + other.samples;
+        let delta: f64 = other.mean - self.mean;
+        self.mean += delta * other.samples as f64 / samples as f64;
+        self.m2 += other.m2 + delta * delta * (self.samples as f64 * other.samples as f64) / samples as f64;
+        self.samples = samples;
+    }
+}
+
+// Weighted accumulation buffer covering the pixels [x0, x0 + width) x [y0, y0 + height)
+#[derive(Clone)]
+pub struct Film {
+    x0: i32,
+    y0: i32,
+    width: i32,
+    height: i32,
+    pixels: Vec<FilmPixel>,
+}
+
+impl Film {
+    pub fn new(x0: i32, y0: i32, width: i32, height: i32) -> Self {
+        let width: i32 = width.max(0);
+        let height: i32 = height.max(0);
+        Self {
+            x0,
+            y0,
+            width,
+            height,
+            pixels: vec![FilmPixel::default(); (width * height) as usize],
+        }
+    }
+
+    pub fn pixel(&self, x: i32, y: i32) -> Option<&FilmPixel> {
+        self.index(x, y).map(|index| &self.pixels[index])
+    }
+
+    // Splats a sample taken at raster position (x, y) into every pixel its filter reaches
+    pub fn add_sample(&mut self, x: f64, y: f64, color: &Color, filter: &Filter) {
+        let radius: f64 = filter.radius();
+        let min_i = (x - 0.5 - radius).ceil() as i32;
+        let max_i = (x - 0.5 + radius).floor() as i32;
+        let min_j = (y - 0.5 - radius).ceil() as i32;
+        let max_j = (y - 0.5 + radius).floor() as i32;
+        for j in min_j..=max_j {
+            for i in min_i..=max_i {
+                let Some(index) = self.index(i, j) else {
+                    continue
+                };
+                let weight: f64 = filter.evaluate(i as f64 + 0.5 - x, j as f64 + 0.5 - y);
+                if weight == 0.0 {
+                    continue
+                }
+                self.pixels[index].weighted_sum += weight * *color;
+                self.pixels[index].weight_sum += weight;
+            }
+        }
+    }
+
+    // Counts a sample taken for pixel (x, y) in its convergence statistics
+    pub fn record_sample(&mut self, x: i32, y: i32, luminance: f64) {
+        if let Some(index) = self.index(x, y) {
+            self.pixels[index].add_luminance(luminance);
+        }
+    }
+
+    // Adds what a sample for pixel (x, y) hit first to its AOV sums
+    pub fn record_first_hit(&mut self, x: i32, y: i32, first_hit: &AovSample) {
+        if let Some(index) = self.index(x, y) {
+            self.pixels[index].aov.add(first_hit);
+        }
+    }
+
+    // Accumulates the overlapping part of another film into this one
+    pub fn merge(&mut self, other: &Film) {
+        for j in other.y0..other.y0 + other.height {
+            for i in other.x0..other.x0 + other.width {
+                let (Some(index), Some(other_index)) = (self.index(i, j), other.index(i, j)) else {
+                    continue
+                };
+                let source: FilmPixel = other.pixels[other_index];
+                self.pixels[index].merge(&source);
+            }
+        }
+    }
+
+    pub fn to_framebuffer(&self) -> FrameBuffer {
+        let mut frame = FrameBuffer::new(self.width, self.height);
+        for j in 0..self.height {
+            for i in 0..self.width {
+                let pixel: &FilmPixel = &self.pixels[(j * self.width + i) as usize];
+                let color: Color = if pixel.weight_sum.abs() > 1e-12 {
+                    pixel.weighted_sum / pixel.weight_sum
+                } else {
+                    Color::default()
+                };
+                frame.set_pixel(i, j, &color);
+                frame.set_sample_count(i, j, pixel.samples);
+                frame.set_aov(i, j, &pixel.aov);
+            }
+        }
+        frame
+    }
+
+    pub fn write(&self, out: &mut impl Write) -> Result<()> {
+        for value in [self.x0, self.y0, self.width, self.height] {
+            out.write_all(&value.to_le_bytes())?;
+        }
+        for pixel in &self.pixels {
+            for value in [pixel.weighted_sum.x(), pixel.weighted_sum.y(), pixel.weighted_sum.z(), pixel.weight_sum] {
+                out.write_all(&value.to_le_bytes())?;
+            }
+            out.write_all(&pixel.samples.to_le_bytes())?;
+            out.write_all(&pixel.mean.to_le_bytes())?;
+            out.write_all(&pixel.m2.to_le_bytes())?;
+            let aov: &AovPixel = &pixel.aov;
+            out.write_all(&aov.hits.to_le_bytes())?;
+            for value in [
+                aov.depth,
+                aov.normal.x(), aov.normal.y(), aov.normal.z(),
+                aov.albedo.x(), aov.albedo.y(), aov.albedo.z(),
+                aov.position.x(), aov.position.y(), aov.position.z(),
+                aov.u, aov.v,
+            ] {
+                out.write_all(&value.to_le_bytes())?;
+            }
+            out.write_all(&aov.object_id.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    // Films wider than `max_width` or taller than `max_height` are rejected before anything is allocated
+    pub fn read(input: &mut impl Read, max_width: i32, max_height: i32) -> Result<Self> {
+        let x0: i32 = read_i32(input)?;
+        let y0: i32 = read_i32(input)?;
+        let width: i32 = read_i32(input)?;
+        let height: i32 = read_i32(input)?;
+        if width < 0 || height < 0 {
+            return Err(Error::new(ErrorKind::InvalidData, "Negative film size"))
+        }
+        if width > max_width || height > max_height {
+            return Err(Error::new(ErrorKind::InvalidData, format!("Film of {width}x{height} is larger than its tile")))
+        }
+        let mut film = Film::new(x0, y0, width, height);
+        for pixel in film.pixels.iter_mut() {
+            pixel.weighted_sum = Color::new(read_f64(input)?, read_f64(input)?, read_f64(input)?);
+            pixel.weight_sum = read_f64(input)?;
+            pixel.samples = read_i32(input)?;
+            pixel.mean = read_f64(input)?;
+            pixel.m2 = read_f64(input)?;
+            pixel.aov.hits = read_i32(input)?;
+            pixel.aov.depth = read_f64(input)?;
+            pixel.aov.normal = Vec3::new(read_f64(input)?, read_f64(input)?, read_f64(input)?);
+            pixel.aov.albedo = Color::new(read_f64(input)?, read_f64(input)?, read_f64(input)?);
+            pixel.aov.position = Point3::new(read_f64(input)?, read_f64(input)?, read_f64(input)?);
+            pixel.aov.u = read_f64(input)?;
+            pixel.aov.v = read_f64(input)?;
+            pixel.aov.object_id = read_i32(input)? as u32;
+        }
+        Ok(film)
+    }
+
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        let (x, y) = (x - self.x0, y - self.y0);
+        if x < 0 || x >= self.width || y < 0 || y >= self.height {
+            return None
+        }
+        Some((y * self.width + x) as usize)
+    }
+}
+
+fn read_i32(input: &mut impl Read) -> Result<i32> {
+    let mut bytes = [0u8; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(i32::from_le_bytes(bytes))
+}
+
+fn read_f64(input: &mut impl Read) -> Result<f64> {
+    let mut bytes = [0u8; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(f64::from_le_bytes(bytes))
+}
@@ -0,0 +1,66 @@
+// Narrower Gaussians would weigh every sample but the exact pixel center as zero
+const MIN_GAUSSIAN_SIGMA: f64 = 0.05;
+
+// Pixel reconstruction filters, evaluated separably at offsets from the pixel center
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Filter {
+    Box { radius: f64 },
+    Tent { radius: f64 },
+    Gaussian { radius: f64, sigma: f64 },
+    MitchellNetravali { radius: f64, b: f64, c: f64 },
+}
+
+impl Filter {
+    pub fn radius(&self) -> f64 {
+        match *self {
+            Self::Box { radius }
+            | Self::Tent { radius }
+            | Self::Gaussian { radius, .. }
+            | Self::MitchellNetravali { radius, .. } => radius.max(0.5),
+        }
+    }
+
+    pub fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.evaluate_1d(x) * self.evaluate_1d(y)
+    }
+
+    fn evaluate_1d(&self, x: f64) -> f64 {
+        let radius: f64 = self.radius();
+        let x: f64 = x.abs();
+        if x > radius {
+            return 0.0
+        }
+        match *self {
+            Self::Box { .. } => 1.0,
+            Self::Tent { .. } => radius - x,
+            Self::Gaussian { sigma, .. } => {
+                let sigma: f64 = sigma.max(MIN_GAUSSIAN_SIGMA);
+                // Shifted down so the filter reaches zero at its radius
+                let gaussian = |d: f64| (-d * d / (2.0 * sigma * sigma)).exp();
+                (gaussian(x) - gaussian(radius)).max(0.0)
+            },
+            Self::MitchellNetravali { b, c, .. } => mitchell(2.0 * x / radius, b, c),
+        }
+    }
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self::Box { radius: 0.5 }
+    }
+}
+
+fn mitchell(x: f64, b: f64, c: f64) -> f64 {
+    if x < 1.0 {
+        return ((12.0 - 9.0 * b - 6.0 * c) * x * x * x
+            + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+            + (6.0 - 2.0 * b)) / 6.0
+    }
+    if x < 2.0 {
+        return ((-b - 6.0 * c) * x * x * x
+            + (6.0 * b + 30.0 * c) * x * x
+            + (-12.0 * b - 48.0 * c) * x
+            + (8.0 * b + 24.0 * c)) / 6.0
+    }
+    0.0
+}
@@ -0,0 +1,139 @@
+use std::io::{Write, Result};
+
+use crate::aov::{Aov, AovPixel};
+use crate::color::{write_color, Color};
+use crate::display::{DisplayTransform, ToneMapping, TransferFunction};
+
+#[derive(Clone)]
+pub struct FrameBuffer {
+    width: i32,
+    height: i32,
+    pixels: Vec<Color>,
+    sample_counts: Vec<i32>,
+    aovs: Vec<AovPixel>,
+    display_transform: DisplayTransform,
+}
+
+impl FrameBuffer {
+    pub fn new(width: i32, height: i32) -> Self {
+        let width: i32 = width.max(0);
+        let height: i32 = height.max(0);
+        Self {
+            width,
+            height,
+            pixels: vec![Color::default(); (width * height) as usize],
+            sample_counts: vec![0; (width * height) as usize],
+            aovs: vec![AovPixel::default(); (width * height) as usize],
+            display_transform: DisplayTransform::default(),
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    // Used by the 8 and 16 bit encoders, float formats stay linear
+    pub fn display_transform(&self) -> &DisplayTransform {
+        &self.display_transform
+    }
+
+    pub fn set_display_transform(&mut self, display_transform: &DisplayTransform) {
+        self.display_transform = *display_transform;
+    }
+
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+
+    pub fn pixel(&self, x: i32, y: i32) -> Color {
+        self.pixels[self.index(x, y)]
+    }
+
+    pub fn set_pixel(&mut self, x: i32, y: i32, color: &Color) {
+        let index: usize = self.index(x, y);
+        self.pixels[index] = *color;
+    }
+
+    pub fn sample_count(&self, x: i32, y: i32) -> i32 {
+        self.sample_counts[self.index(x, y)]
+    }
+
+    pub fn set_sample_count(&mut self, x: i32, y: i32, samples: i32) {
+        let index: usize = self.index(x, y);
+        self.sample_counts[index] = samples;
+    }
+
+    pub fn set_aov(&mut self, x: i32, y: i32, aov: &AovPixel) {
+        let index: usize = self.index(x, y);
+        self.aovs[index] = *aov;
+    }
+
+    // Overwrites the pixels under `source`, placed with its top left corner at (x0, y0)
+    pub fn copy_from(&mut self, source: &FrameBuffer, x0: i32, y0: i32) {
+        for j in 0..source.height {
+            for i in 0..source.width {
+                let (x, y) = (x0 + i, y0 + j);
+                if x < 0 || x >= self.width || y < 0 || y >= self.height {
+                    continue
+                }
+                let index: usize = self.index(x, y);
+                let source_index: usize = source.index(i, j);
+                self.pixels[index] = source.pixels[source_index];
+                self.sample_counts[index] = source.sample_counts[source_index];
+                self.aovs[index] = source.aovs[source_index];
+            }
+        }
+    }
+
+    // Samples taken per pixel, from blue (fewest) to red (most)
+    pub fn sample_heatmap(&self) -> FrameBuffer {
+        let mut heatmap = FrameBuffer::new(self.width, self.height);
+        heatmap.set_display_transform(
+            &DisplayTransform::new(0.0, ToneMapping::Clamp, TransferFunction::Linear)
+        );
+        let max_samples: i32 = self.sample_counts.iter().copied().max().unwrap_or(0).max(1);
+        for (index, samples) in self.sample_counts.iter().enumerate() {
+            let t: f64 = *samples as f64 / max_samples as f64;
+            heatmap.pixels[index] = Color::new(
+                (2.0 * t - 0.5).clamp(0.0, 1.0),
+                1.0 - (2.0 * t - 1.0).abs(),
+                (1.5 - 2.0 * t).clamp(0.0, 1.0),
+            );
+        }
+        heatmap
+    }
+
+    // One auxiliary buffer as its own frame, written without any display encoding
+    pub fn aov(&self, aov: Aov) -> FrameBuffer {
+        let mut frame = FrameBuffer::new(self.width, self.height);
+        frame.set_display_transform(
+            &DisplayTransform::new(0.0, ToneMapping::Clamp, TransferFunction::Linear)
+        );
+        for (index, pixel) in self.aovs.iter().enumerate() {
+            frame.pixels[index] = pixel.value(aov);
+        }
+        frame
+    }
+
+    // ASCII PPM (P3), display encoded
+    pub fn write_ppm(&self, out: &mut impl Write) -> Result<()> {
+        writeln!(out, "P3")?;
+        writeln!(out, "{} {}", self.width, self.height)?;
+        writeln!(out, "255")?;
+        for pixel_color in &self.pixels {
+            write_color(out, pixel_color, &self.display_transform)?;
+        }
+        Ok(())
+    }
+
+    fn index(&self, x: i32, y: i32) -> usize {
+        if x < 0 || x >= self.width || y < 0 || y >= self.height {
+            panic!("Pixel ({x}, {y}) out of range")
+        }
+        (y * self.width + x) as usize
+    }
+}
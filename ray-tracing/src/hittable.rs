@@ -1,8 +1,8 @@
-use crate::aabb::AABB;
+use crate::aabb::{self, AABB};
 use crate::interval::Interval;
 use crate::material::{Lambertian, Material};
 use crate::ray::Ray;
-use crate::utilities::{INFINITY, degrees_to_radians};
+use crate::utilities::{INFINITY, PI, degrees_to_radians};
 use crate::vec3::{dot, Point3, Vec3};
 
 #[derive(Clone)]
@@ -14,6 +14,7 @@ pub struct HitRecord {
     pub u: f64,
     pub v: f64,
     pub front_face: bool,
+    pub object_id: u32,
 }
 
 impl HitRecord {
@@ -28,15 +29,16 @@ impl HitRecord {
         Self {
             point: *point,
             normal: *normal,
-            material: material,
+            material,
             time,
             u,
             v,
             front_face,
+            object_id: 0,
         }
     }
 
-    pub fn set_face_normal(&mut self, ray: &Ray, outward_normal: &Vec3) -> () {
+    pub fn set_face_normal(&mut self, ray: &Ray, outward_normal: &Vec3) {
         self.front_face = dot(ray.direction(), outward_normal) < 0.0;
         self.normal = if self.front_face {
             *outward_normal
@@ -56,13 +58,24 @@ impl Default for HitRecord {
             v: 0.0,
             time: 0.0,
             front_face: false,
+            object_id: 0,
         }
     }
 }
 
-pub trait Hittable: HittableClone {
+pub trait Hittable: HittableClone + Send + Sync {
     fn hit(&self, ray: &Ray, interval: &Interval, record: &mut HitRecord) -> bool;
     fn bounding_box(&self) -> AABB;
+
+    // Density over solid angle of `sample_direction` giving `direction` from `origin`, 0 if it misses
+    fn pdf_value(&self, _origin: &Point3, _direction: &Vec3, _time: f64) -> f64 {
+        0.0
+    }
+
+    // Direction from `origin` towards a point on the object, from two uniform numbers
+    fn sample_direction(&self, _origin: &Point3, _time: f64, _u: f64, _v: f64) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
 }
 
 pub trait HittableClone {
@@ -112,6 +125,14 @@ impl Hittable for Translate {
     fn bounding_box(&self) -> AABB {
         self.bbox
     }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        self.object.pdf_value(&(*origin - self.offset), direction, time)
+    }
+
+    fn sample_direction(&self, origin: &Point3, time: f64, u: f64, v: f64) -> Vec3 {
+        self.object.sample_direction(&(*origin - self.offset), time, u, v)
+    }
 }
 
 #[derive(Clone)]
@@ -154,41 +175,188 @@ impl RotateY {
             bbox,
         }
     }
+
+    fn to_object(&self, v: &Vec3) -> Vec3 {
+        rotate_to_object(v, self.sin_theta, self.cos_theta)
+    }
+
+    fn to_world(&self, v: &Vec3) -> Vec3 {
+        rotate_to_world(v, self.sin_theta, self.cos_theta)
+    }
 }
 
 impl Hittable for RotateY {
     fn hit(&self, ray: &Ray, interval: &Interval, record: &mut HitRecord) -> bool {
         // Transform
-        let origin = Point3::new(
-            (self.cos_theta * ray.origin().x()) - (self.sin_theta * ray.origin().z()), 
-            ray.origin().y(),
-            (self.sin_theta * ray.origin().x()) + (self.cos_theta * ray.origin().z()),
-        );
-        let direction = Vec3::new(
-            (self.cos_theta * ray.direction().x()) - (self.sin_theta * ray.direction().z()), 
-            ray.direction().y(),
-            (self.sin_theta * ray.direction().x()) + (self.cos_theta * ray.direction().z()),
-        );
-        let rotated_ray = Ray::new(&origin, &direction, Some(ray.time()));
+        let rotated_ray = Ray::new(&self.to_object(ray.origin()), &self.to_object(ray.direction()), Some(ray.time()));
         // Check intersection
         if !self.object.hit(&rotated_ray, interval, record) {
             return false
         }
         // Transform
-        record.point = Point3::new(
-            (self.cos_theta * record.point.x()) + (self.sin_theta * record.point.z()), 
-            record.point.y(), 
-            (- self.sin_theta * record.point.x()) + (self.cos_theta * record.point.z()),
-        );
-        record.normal = Vec3::new(
-            (self.cos_theta * record.normal.x()) + (self.sin_theta * record.normal.z()), 
-            record.normal.y(), 
-            (- self.sin_theta * record.normal.x()) + (self.cos_theta * record.normal.z()),
-        );
+        record.point = self.to_world(&record.point);
+        record.normal = self.to_world(&record.normal);
+        true
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        self.object.pdf_value(&self.to_object(origin), &self.to_object(direction), time)
+    }
+
+    fn sample_direction(&self, origin: &Point3, time: f64, u: f64, v: f64) -> Vec3 {
+        self.to_world(&self.object.sample_direction(&self.to_object(origin), time, u, v))
+    }
+}
+
+// Placement of an object at one point in time: rotated about y by `angle` degrees, then moved by `offset`.
+// Time is in frames like `CameraKey`, so objects and the camera share one timeline across a sequence.
+#[derive(Clone, Copy, Default)]
+pub struct TransformKey {
+    pub time: f64,
+    pub offset: Vec3,
+    pub angle: f64,
+}
+
+// Moves any object along keyframed transforms, linearly interpolated over the ray time, which is the
+// frame plus a point in the shutter
+#[derive(Clone)]
+pub struct Keyframed {
+    object: Box<dyn Hittable>,
+    keys: Vec<TransformKey>,
+    bbox: AABB,
+}
+
+impl Keyframed {
+    // Keys may be given in any order, the object is held at the first and last key outside their range
+    pub fn new(object: Box<dyn Hittable>, keys: &[TransformKey]) -> Self {
+        let mut keys: Vec<TransformKey> = keys.to_vec();
+        if keys.is_empty() {
+            keys.push(TransformKey::default());
+        }
+        keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+        let bounds: AABB = object.bounding_box();
+        let mut bbox: AABB = aabb::EMPTY;
+        for index in 0..keys.len() {
+            let (k1, k2) = (&keys[index], &keys[(index + 1).min(keys.len() - 1)]);
+            bbox = AABB::new_from_children(&bbox, &swept_bounds(&bounds, k1, k2));
+        }
+        Self {
+            object,
+            keys,
+            bbox,
+        }
+    }
+
+    // Offset, sine and cosine of the rotation at `time`
+    fn transform_at(&self, time: f64) -> (Vec3, f64, f64) {
+        let next: usize = self.keys.partition_point(|k| k.time <= time);
+        let key: TransformKey = if next == 0 {
+            self.keys[0]
+        } else if next == self.keys.len() {
+            self.keys[next - 1]
+        } else {
+            let (k1, k2) = (&self.keys[next - 1], &self.keys[next]);
+            let t: f64 = (time - k1.time) / (k2.time - k1.time);
+            TransformKey {
+                time,
+                offset: (1.0 - t) * k1.offset + t * k2.offset,
+                angle: k1.angle + t * (k2.angle - k1.angle),
+            }
+        };
+        let radians: f64 = degrees_to_radians(key.angle);
+        (key.offset, radians.sin(), radians.cos())
+    }
+}
+
+impl Hittable for Keyframed {
+    fn hit(&self, ray: &Ray, interval: &Interval, record: &mut HitRecord) -> bool {
+        let (offset, sin_theta, cos_theta) = self.transform_at(ray.time());
+        // Transform
+        let origin: Point3 = rotate_to_object(&(*ray.origin() - offset), sin_theta, cos_theta);
+        let direction: Vec3 = rotate_to_object(ray.direction(), sin_theta, cos_theta);
+        let object_ray = Ray::new(&origin, &direction, Some(ray.time()));
+        // Check intersection
+        if !self.object.hit(&object_ray, interval, record) {
+            return false
+        }
+        // Transform
+        record.point = rotate_to_world(&record.point, sin_theta, cos_theta) + offset;
+        record.normal = rotate_to_world(&record.normal, sin_theta, cos_theta);
         true
     }
 
     fn bounding_box(&self) -> AABB {
         self.bbox
     }
-}
\ No newline at end of file
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        let (offset, sin_theta, cos_theta) = self.transform_at(time);
+        let object_origin: Point3 = rotate_to_object(&(*origin - offset), sin_theta, cos_theta);
+        self.object.pdf_value(&object_origin, &rotate_to_object(direction, sin_theta, cos_theta), time)
+    }
+
+    fn sample_direction(&self, origin: &Point3, time: f64, u: f64, v: f64) -> Vec3 {
+        let (offset, sin_theta, cos_theta) = self.transform_at(time);
+        let object_origin: Point3 = rotate_to_object(&(*origin - offset), sin_theta, cos_theta);
+        rotate_to_world(&self.object.sample_direction(&object_origin, time, u, v), sin_theta, cos_theta)
+    }
+}
+
+// Undoes a rotation about y, from world to object space
+fn rotate_to_object(v: &Vec3, sin_theta: f64, cos_theta: f64) -> Vec3 {
+    Vec3::new(
+        (cos_theta * v.x()) - (sin_theta * v.z()),
+        v.y(),
+        (sin_theta * v.x()) + (cos_theta * v.z()),
+    )
+}
+
+fn rotate_to_world(v: &Vec3, sin_theta: f64, cos_theta: f64) -> Vec3 {
+    Vec3::new(
+        (cos_theta * v.x()) + (sin_theta * v.z()),
+        v.y(),
+        (- sin_theta * v.x()) + (cos_theta * v.z()),
+    )
+}
+
+// Everything the box covers while moving from k1 to k2: each corner sweeps a circular arc about y,
+// which is then slid across every offset in between
+fn swept_bounds(bounds: &AABB, k1: &TransformKey, k2: &TransformKey) -> AABB {
+    let start: f64 = degrees_to_radians(k1.angle.min(k2.angle));
+    let end: f64 = degrees_to_radians(k1.angle.max(k2.angle));
+    let mut min = Point3::new(INFINITY, INFINITY, INFINITY);
+    let mut max = Point3::new(-INFINITY, -INFINITY, -INFINITY);
+    for i in 0..2 {
+        for j in 0..2 {
+            for k in 0..2 {
+                let x: f64 = i as f64 * bounds.x.max + (1 - i) as f64 * bounds.x.min;
+                let y: f64 = j as f64 * bounds.y.max + (1 - j) as f64 * bounds.y.min;
+                let z: f64 = k as f64 * bounds.z.max + (1 - k) as f64 * bounds.z.min;
+                // Along the arc x and z peak where the corner crosses an axis, a quarter turn apart
+                let phase: f64 = z.atan2(x);
+                let mut angles: Vec<f64> = vec![start, end];
+                let mut angle: f64 = phase + ((start - phase) / (PI / 2.0)).ceil() * (PI / 2.0);
+                while angle < end {
+                    angles.push(angle);
+                    angle += PI / 2.0;
+                }
+                for theta in angles {
+                    let tester = Vec3::new(theta.cos() * x + theta.sin() * z, y, - theta.sin() * x + theta.cos() * z);
+                    for c in 0..3 {
+                        min[c] = min[c].min(tester[c]);
+                        max[c] = max[c].max(tester[c]);
+                    }
+                }
+            }
+        }
+    }
+    for c in 0..3 {
+        min[c] += k1.offset[c].min(k2.offset[c]);
+        max[c] += k1.offset[c].max(k2.offset[c]);
+    }
+    AABB::new_from_points(&min, &max)
+}
@@ -4,6 +4,7 @@ use crate::aabb::AABB;
 use crate::hittable::{HitRecord, Hittable};
 use crate::interval::{Interval};
 use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
 
 #[derive(Default, Clone)]
 pub struct HittableList {
@@ -18,11 +19,11 @@ impl HittableList {
         list
     }
 
-    pub fn clear(&mut self) -> () {
+    pub fn clear(&mut self) {
         self.objects.clear();
     }
 
-    pub fn add(&mut self, object: Box<dyn Hittable>) -> () {
+    pub fn add(&mut self, object: Box<dyn Hittable>) {
         self.bbox = AABB::new_from_children(&self.bbox, &object.bounding_box());
         self.objects.push(object);
     }
@@ -52,4 +53,23 @@ impl Hittable for HittableList {
     fn bounding_box(&self) -> AABB {
         self.bbox
     }
+
+    // Every object is picked with the same probability
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        if self.objects.is_empty() {
+            return 0.0
+        }
+        let weight: f64 = 1.0 / self.objects.len() as f64;
+        self.objects.iter().map(|object| weight * object.pdf_value(origin, direction, time)).sum()
+    }
+
+    // `u` picks the object, then what is left of it is reused within that object
+    fn sample_direction(&self, origin: &Point3, time: f64, u: f64, v: f64) -> Vec3 {
+        if self.objects.is_empty() {
+            return Vec3::new(1.0, 0.0, 0.0)
+        }
+        let count: f64 = self.objects.len() as f64;
+        let index: f64 = (u * count).floor().min(count - 1.0);
+        self.objects[index as usize].sample_direction(origin, time, u * count - index, v)
+    }
 }
\ No newline at end of file
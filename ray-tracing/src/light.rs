@@ -0,0 +1,161 @@
+use crate::color::Color;
+use crate::utilities::{degrees_to_radians, INFINITY, PI};
+use crate::vec3::{dot, unit_vector, Point3, Vec3};
+
+// Light arriving at a point from a delta light, which no ray can hit
+pub struct LightSample {
+    // Unit vector from the point towards the light
+    pub direction: Vec3,
+    // How far along `direction` the light is, infinite for directional lights
+    pub distance: f64,
+    // Irradiance on a surface facing the light, in W/m^2
+    pub irradiance: Color,
+}
+
+// Infinitely small or infinitely far lights, reachable only through shadow rays
+pub trait Light: LightClone + Send + Sync {
+    // None when the point receives no light from it
+    fn sample(&self, point: &Point3) -> Option<LightSample>;
+}
+
+pub trait LightClone {
+    fn clone_box(&self) -> Box<dyn Light>;
+}
+
+impl<T> LightClone for T where T: 'static + Light + Clone, {
+    fn clone_box(&self) -> Box<dyn Light> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn Light> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+// Shines equally in every direction, intensity in W/sr with scene units taken as metres
+#[derive(Clone)]
+pub struct PointLight {
+    position: Point3,
+    color: Color,
+    intensity: f64,
+}
+
+impl PointLight {
+    pub fn new(position: &Point3, color: &Color, intensity: f64) -> Self {
+        Self {
+            position: *position,
+            color: *color,
+            intensity,
+        }
+    }
+
+    // Total emitted power in watts, spread over the whole sphere
+    pub fn new_from_power(position: &Point3, color: &Color, power: f64) -> Self {
+        Self::new(position, color, power / (4.0 * PI))
+    }
+}
+
+impl Light for PointLight {
+    fn sample(&self, point: &Point3) -> Option<LightSample> {
+        let to_light: Vec3 = self.position - *point;
+        let distance_squared: f64 = to_light.length_squared();
+        if distance_squared <= 0.0 {
+            return None
+        }
+        Some(LightSample {
+            direction: unit_vector(&to_light),
+            distance: distance_squared.sqrt(),
+            irradiance: self.intensity / distance_squared * self.color,
+        })
+    }
+}
+
+// Point light limited to a cone, full intensity inside `inner_angle` fading out to nothing at
+// `outer_angle`, both in degrees from its axis
+#[derive(Clone)]
+pub struct SpotLight {
+    position: Point3,
+    direction: Vec3,
+    color: Color,
+    intensity: f64,
+    cos_inner: f64,
+    cos_outer: f64,
+}
+
+impl SpotLight {
+    pub fn new(position: &Point3, direction: &Vec3, color: &Color, intensity: f64, inner_angle: f64, outer_angle: f64) -> Self {
+        let outer_angle: f64 = outer_angle.clamp(0.0, 180.0);
+        let inner_angle: f64 = inner_angle.clamp(0.0, outer_angle);
+        Self {
+            position: *position,
+            direction: unit_vector(direction),
+            color: *color,
+            intensity,
+            cos_inner: degrees_to_radians(inner_angle).cos(),
+            cos_outer: degrees_to_radians(outer_angle).cos(),
+        }
+    }
+
+    // Smooth falloff across the edge of the cone
+    fn falloff(&self, cos_theta: f64) -> f64 {
+        if cos_theta >= self.cos_inner {
+            return 1.0
+        }
+        if cos_theta <= self.cos_outer {
+            return 0.0
+        }
+        let t: f64 = (cos_theta - self.cos_outer) / (self.cos_inner - self.cos_outer);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, point: &Point3) -> Option<LightSample> {
+        let to_light: Vec3 = self.position - *point;
+        let distance_squared: f64 = to_light.length_squared();
+        if distance_squared <= 0.0 {
+            return None
+        }
+        let direction: Vec3 = unit_vector(&to_light);
+        let falloff: f64 = self.falloff(dot(&-direction, &self.direction));
+        if falloff <= 0.0 {
+            return None
+        }
+        Some(LightSample {
+            direction,
+            distance: distance_squared.sqrt(),
+            irradiance: falloff * self.intensity / distance_squared * self.color,
+        })
+    }
+}
+
+// Parallel light from infinitely far away such as the sun, `direction` is the way the light travels
+// and `irradiance` is in W/m^2 on a surface facing it
+#[derive(Clone)]
+pub struct DirectionalLight {
+    direction: Vec3,
+    color: Color,
+    irradiance: f64,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: &Vec3, color: &Color, irradiance: f64) -> Self {
+        Self {
+            direction: unit_vector(direction),
+            color: *color,
+            irradiance,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, _point: &Point3) -> Option<LightSample> {
+        Some(LightSample {
+            direction: -self.direction,
+            distance: INFINITY,
+            irradiance: self.irradiance * self.color,
+        })
+    }
+}
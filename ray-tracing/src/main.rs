@@ -1,42 +1,65 @@
 pub mod aabb;
+pub mod animation;
+pub mod aov;
+pub mod aperture;
 pub mod bvh;
 pub mod camera;
+pub mod checkpoint;
 pub mod color;
 pub mod constant_medium;
+pub mod denoise;
+pub mod display;
+pub mod environment;
+pub mod film;
+pub mod filter;
+pub mod framebuffer;
 pub mod hittable;
 pub mod hittable_list;
 pub mod interval;
+pub mod light;
 pub mod material;
+pub mod onb;
+pub mod output;
 pub mod perlin;
 pub mod quad;
 pub mod ray;
 pub mod rtw_image;
+pub mod sampler;
+pub mod sky;
 pub mod sphere;
 pub mod texture;
 pub mod utilities;
 pub mod vec3;
 
 use indicatif::{ProgressBar, ProgressStyle};
-use std::fs::{create_dir_all, File};
-use std::io::Result;
+use std::fs::create_dir_all;
+use std::io::{Error, Result};
 
+use crate::animation::{CameraAnimation, CameraKey, Interpolation};
 use crate::bvh::BVHNode;
-use crate::camera::Camera;
+use crate::camera::{interrupt, Camera};
 use crate::color::Color;
 use crate::constant_medium::ConstantMedium;
-use crate::hittable::{Hittable, RotateY, Translate};
+use crate::denoise::Denoiser;
+use crate::display::{DisplayTransform, ToneMapping, TransferFunction};
+use crate::environment::EnvironmentMap;
+use crate::framebuffer::FrameBuffer;
+use crate::hittable::{Hittable, Keyframed, RotateY, TransformKey, Translate};
 use crate::hittable_list::HittableList;
 use crate::interval::Interval;
+use crate::light::{DirectionalLight, Light, PointLight, SpotLight};
 use crate::material::{Dielectric, DiffuseLight, Lambertian, Metal};
+use crate::output::{frame_path, write_aovs, write_image};
 use crate::quad::{Quad, make_box};
+use crate::sky::{sun_direction, Sky};
 use crate::sphere::Sphere;
 use crate::texture::{CheckerTexture, ImageTexture, NoiseTexture};
-use crate::utilities::random_double;
-use crate::vec3::{random, Point3, Vec3};
+use crate::utilities::{random_double, seed_random, PI};
+use crate::vec3::{random, unit_vector, Point3, Vec3};
 
-fn bouncing_spheres() -> Result<()> {
+fn bouncing_spheres(seed: u64) -> Result<()> {
     // Output
-    let mut image = File::create("./output/bouncing_spheres.ppm")?;
+    let output_path = "./output/bouncing_spheres.png";
 
     let bar = ProgressBar::new(485);
     bar.set_message("Generating objects...");
@@ -45,6 +68,7 @@ fn bouncing_spheres() -> Result<()> {
         .progress_chars("#>-"));
 
     // World
+    seed_random(seed);
     let mut world = HittableList::default();
 
     let checker = CheckerTexture::new(0.32, &Color::new(0.2, 0.3, 0.1), &Color::new(0.9, 0.9, 0.9));
@@ -105,9 +129,12 @@ fn bouncing_spheres() -> Result<()> {
     
     //Render
     let mut camera = Camera::default();
+    camera.seed = seed;
     camera.aspect_ratio = 16.0 / 9.0;
     camera.image_width = 400;
     camera.samples_per_pixel = 100;
+    camera.min_samples_per_pixel = 16;
+    camera.adaptive_threshold = 0.02;
     camera.max_depth = 50;
     camera.background = Color::new(0.7, 0.8, 1.0);
     camera.vertical_view_angle = 20.0;
@@ -116,14 +143,16 @@ fn bouncing_spheres() -> Result<()> {
     camera.view_up = Vec3::new(0.0, 1.0, 0.0);
     camera.defocus_angle = 0.6;
     camera.focus_distance = 10.0;
-    camera.render(&mut world, &mut image)?;
+    let frame: FrameBuffer = camera.render(&world);
+    write_image(&frame, output_path, None)?;
+    write_image(&frame.sample_heatmap(), "./output/bouncing_spheres_samples.png", None)?;
 
     Ok(())
 }
 
-fn checkered_spheres() -> Result<()> {
+fn checkered_spheres(seed: u64) -> Result<()> {
     // Output
-    let mut image = File::create("./output/checkered_spheres.ppm")?;
+    let output_path = "./output/checkered_spheres.png";
 
     let bar = ProgressBar::new(2);
     bar.set_message("Generating objects...");
@@ -132,6 +161,7 @@ fn checkered_spheres() -> Result<()> {
         .progress_chars("#>-"));
 
     // World
+    seed_random(seed);
     let mut world = HittableList::default();
 
     let checker = CheckerTexture::new(0.32, &Color::new(0.2, 0.3, 0.1), &Color::new(0.9, 0.9, 0.9));
@@ -144,6 +174,7 @@ fn checkered_spheres() -> Result<()> {
     
     //Render
     let mut camera = Camera::default();
+    camera.seed = seed;
     camera.aspect_ratio = 16.0 / 9.0;
     camera.image_width = 400;
     camera.samples_per_pixel = 100;
@@ -154,14 +185,15 @@ fn checkered_spheres() -> Result<()> {
     camera.look_at = Point3::default();
     camera.view_up = Vec3::new(0.0, 1.0, 0.0);
     camera.defocus_angle = 0.0;
-    camera.render(&mut world, &mut image)?;
+    let frame: FrameBuffer = camera.render(&world);
+    write_image(&frame, output_path, None)?;
 
     Ok(())
 }
 
-fn earth() -> Result<()> {
+fn earth(seed: u64) -> Result<()> {
     // Output
-    let mut image = File::create("./output/earth.ppm")?;
+    let output_path = "./output/earth.png";
 
     let bar = ProgressBar::new(1);
     bar.set_message("Generating objects...");
@@ -170,6 +202,7 @@ fn earth() -> Result<()> {
         .progress_chars("#>-"));
 
     // World
+    seed_random(seed);
     let mut world = HittableList::default();
 
     let earth_texture = ImageTexture::new("earthmap.jpg");
@@ -181,6 +214,7 @@ fn earth() -> Result<()> {
     
     //Render
     let mut camera = Camera::default();
+    camera.seed = seed;
     camera.aspect_ratio = 16.0 / 9.0;
     camera.image_width = 400;
     camera.samples_per_pixel = 100;
@@ -191,14 +225,15 @@ fn earth() -> Result<()> {
     camera.look_at = Point3::default();
     camera.view_up = Vec3::new(0.0, 1.0, 0.0);
     camera.defocus_angle = 0.0;
-    camera.render(&mut world, &mut image)?;
+    let frame: FrameBuffer = camera.render(&world);
+    write_image(&frame, output_path, None)?;
 
     Ok(())
 }
 
-fn perlin_spheres() -> Result<()> {
+fn perlin_spheres(seed: u64) -> Result<()> {
     // Output
-    let mut image = File::create("./output/perlin_spheres.ppm")?;
+    let output_path = "./output/perlin_spheres.png";
 
     let bar = ProgressBar::new(2);
     bar.set_message("Generating objects...");
@@ -207,6 +242,7 @@ fn perlin_spheres() -> Result<()> {
         .progress_chars("#>-"));
 
     // World
+    seed_random(seed);
     let mut world = HittableList::default();
 
     let pertext = NoiseTexture::new(4.0);
@@ -219,6 +255,7 @@ fn perlin_spheres() -> Result<()> {
     
     //Render
     let mut camera = Camera::default();
+    camera.seed = seed;
     camera.aspect_ratio = 16.0 / 9.0;
     camera.image_width = 400;
     camera.samples_per_pixel = 100;
@@ -229,14 +266,15 @@ fn perlin_spheres() -> Result<()> {
     camera.look_at = Point3::default();
     camera.view_up = Vec3::new(0.0, 1.0, 0.0);
     camera.defocus_angle = 0.0;
-    camera.render(&mut world, &mut image)?;
+    let frame: FrameBuffer = camera.render(&world);
+    write_image(&frame, output_path, None)?;
 
     Ok(())
 }
 
-fn quads() -> Result<()> {
+fn quads(seed: u64) -> Result<()> {
     // Output
-    let mut image = File::create("./output/quads.ppm")?;
+    let output_path = "./output/quads.png";
 
     let bar = ProgressBar::new(5);
     bar.set_message("Generating objects...");
@@ -245,6 +283,7 @@ fn quads() -> Result<()> {
         .progress_chars("#>-"));
 
     // World
+    seed_random(seed);
     let mut world = HittableList::default();
 
     // Materials
@@ -266,6 +305,7 @@ fn quads() -> Result<()> {
 
     //Render
     let mut camera = Camera::default();
+    camera.seed = seed;
     camera.aspect_ratio = 1.0;
     camera.image_width = 400;
     camera.samples_per_pixel = 100;
@@ -276,14 +316,15 @@ fn quads() -> Result<()> {
     camera.look_at = Point3::default();
     camera.view_up = Vec3::new(0.0, 1.0, 0.0);
     camera.defocus_angle = 0.0;
-    camera.render(&mut world, &mut image)?;
+    let frame: FrameBuffer = camera.render(&world);
+    write_image(&frame, output_path, None)?;
 
     Ok(())
 }
 
-fn simple_light() -> Result<()> {
+fn simple_light(seed: u64) -> Result<()> {
     // Output
-    let mut image = File::create("./output/simple_light.ppm")?;
+    let output_path = "./output/simple_light.png";
 
     let bar = ProgressBar::new(3);
     bar.set_message("Generating objects...");
@@ -292,6 +333,7 @@ fn simple_light() -> Result<()> {
         .progress_chars("#>-"));
 
     // World
+    seed_random(seed);
     let mut world = HittableList::default();
 
     let pertext = NoiseTexture::new(4.0);
@@ -300,13 +342,17 @@ fn simple_light() -> Result<()> {
     world.add(Box::new(Sphere::new(&Point3::new(0.0, 2.0, 0.0), None, 2.0, pertext_material.clone())));
 
     let difflight = Box::new(DiffuseLight::new(&Color::new(4.0, 4.0, 4.0)));
-    world.add(Box::new(Quad::new(&Point3::new(3.0, 1.0, -2.0), &Vec3::new(2.0, 0.0, 0.0), &Vec3::new(0.0, 2.0, 0.0), difflight.clone())));
+    let mut lights = HittableList::default();
+    let light_quad: Box<dyn Hittable> = Box::new(Quad::new(&Point3::new(3.0, 1.0, -2.0), &Vec3::new(2.0, 0.0, 0.0), &Vec3::new(0.0, 2.0, 0.0), difflight.clone()));
+    world.add(light_quad.clone());
+    lights.add(light_quad);
 
     bar.set_message("Generating objects: Done.");
     bar.finish();
 
     //Render
     let mut camera = Camera::default();
+    camera.seed = seed;
     camera.aspect_ratio = 16.0 / 9.0;
     camera.image_width = 400;
     camera.samples_per_pixel = 100;
@@ -317,14 +363,18 @@ fn simple_light() -> Result<()> {
     camera.look_at = Point3::new(0.0, 2.0, 0.0);
     camera.view_up = Vec3::new(0.0, 1.0, 0.0);
     camera.defocus_angle = 0.0;
-    camera.render(&mut world, &mut image)?;
+    camera.lights = lights;
+    camera.display_transform = DisplayTransform::new(0.0, ToneMapping::AcesFitted, TransferFunction::Srgb);
+    let frame: FrameBuffer = camera.render(&world);
+    write_image(&frame, output_path, None)?;
+    write_image(&Denoiser::default().denoise(&frame), "./output/simple_light_denoised.png", None)?;
 
     Ok(())
 }
 
-fn cornell_box() -> Result<()> {
+fn cornell_box(seed: u64) -> Result<()> {
     // Output
-    let mut image = File::create("./output/cornell_box.ppm")?;
+    let output_path = "./output/cornell_box.png";
 
     let bar = ProgressBar::new(8);
     bar.set_message("Generating objects...");
@@ -333,6 +383,7 @@ fn cornell_box() -> Result<()> {
         .progress_chars("#>-"));
 
     // World
+    seed_random(seed);
     let mut world = HittableList::default();
 
     let red = Box::new(Lambertian::new(&Color::new(0.65, 0.05, 0.05)));
@@ -342,7 +393,10 @@ fn cornell_box() -> Result<()> {
 
     world.add(Box::new(Quad::new(&Point3::new(555.0, 0.0, 0.0), &Vec3::new(0.0, 555.0, 0.0), &Vec3::new(0.0, 0.0, 555.0), green.clone())));
     world.add(Box::new(Quad::new(&Point3::default(), &Vec3::new(0.0, 555.0, 0.0), &Vec3::new(0.0, 0.0, 555.0), red.clone())));
-    world.add(Box::new(Quad::new(&Point3::new(343.0, 554.0, 332.0), &Vec3::new(-130.0, 0.0, 0.0), &Vec3::new(0.0, 0.0, -105.0), light.clone())));
+    let mut lights = HittableList::default();
+    let light_quad: Box<dyn Hittable> = Box::new(Quad::new(&Point3::new(343.0, 554.0, 332.0), &Vec3::new(-130.0, 0.0, 0.0), &Vec3::new(0.0, 0.0, -105.0), light.clone()));
+    world.add(light_quad.clone());
+    lights.add(light_quad);
     world.add(Box::new(Quad::new(&Point3::default(), &Vec3::new(555.0, 0.0, 0.0), &Vec3::new(0.0, 0.0, 555.0), white.clone())));
     world.add(Box::new(Quad::new(&Point3::new(555.0, 555.0, 555.0), &Vec3::new(-555.0, 0.0, 0.0), &Vec3::new(0.0, 0.0, -555.0), white.clone())));
     world.add(Box::new(Quad::new(&Point3::new(0.0, 0.0, 555.0), &Vec3::new(555.0, 0.0, 0.0), &Vec3::new(0.0, 555.0, 0.0), white.clone())));
@@ -362,6 +416,7 @@ fn cornell_box() -> Result<()> {
 
     //Render
     let mut camera = Camera::default();
+    camera.seed = seed;
     camera.aspect_ratio = 1.0;
     camera.image_width = 600;
     camera.samples_per_pixel = 200;
@@ -372,14 +427,24 @@ fn cornell_box() -> Result<()> {
     camera.look_at = Point3::new(278.0, 278.0, 0.0);
     camera.view_up = Vec3::new(0.0, 1.0, 0.0);
     camera.defocus_angle = 0.0;
-    camera.render(&mut world, &mut image)?;
+    camera.lights = lights;
+    camera.checkpoint_path = Some("./output/cornell_box.checkpoint".to_string());
+    camera.samples_per_pass = 8;
+    // Refresh the image after every pass so the render can be watched as it converges
+    let frame: FrameBuffer = camera.render_progressive(&world, |frame| {
+        if let Err(error) = write_image(frame, output_path, None) {
+            eprintln!("Could not write {output_path}: {error}");
+        }
+    });
+    write_image(&frame, output_path, None)?;
+    write_aovs(&frame, "./output/cornell_box.exr", None)?;
 
     Ok(())
 }
 
-fn cornell_smoke() -> Result<()> {
+fn cornell_smoke(seed: u64) -> Result<()> {
     // Output
-    let mut image = File::create("./output/cornell_smoke.ppm")?;
+    let output_path = "./output/cornell_smoke.png";
 
     let bar = ProgressBar::new(8);
     bar.set_message("Generating objects...");
@@ -388,6 +453,7 @@ fn cornell_smoke() -> Result<()> {
         .progress_chars("#>-"));
 
     // World
+    seed_random(seed);
     let mut world = HittableList::default();
 
     let red = Box::new(Lambertian::new(&Color::new(0.65, 0.05, 0.05)));
@@ -397,7 +463,10 @@ fn cornell_smoke() -> Result<()> {
 
     world.add(Box::new(Quad::new(&Point3::new(555.0, 0.0, 0.0), &Vec3::new(0.0, 555.0, 0.0), &Vec3::new(0.0, 0.0, 555.0), green.clone())));
     world.add(Box::new(Quad::new(&Point3::default(), &Vec3::new(0.0, 555.0, 0.0), &Vec3::new(0.0, 0.0, 555.0), red.clone())));
-    world.add(Box::new(Quad::new(&Point3::new(113.0, 554.0, 127.0), &Vec3::new(330.0, 0.0, 0.0), &Vec3::new(0.0, 0.0, 305.0), light.clone())));
+    let mut lights = HittableList::default();
+    let light_quad: Box<dyn Hittable> = Box::new(Quad::new(&Point3::new(113.0, 554.0, 127.0), &Vec3::new(330.0, 0.0, 0.0), &Vec3::new(0.0, 0.0, 305.0), light.clone()));
+    world.add(light_quad.clone());
+    lights.add(light_quad);
     world.add(Box::new(Quad::new(&Point3::new(0.0, 555.0, 0.0), &Vec3::new(555.0, 0.0, 0.0), &Vec3::new(0.0, 0.0, 555.0), white.clone())));
     world.add(Box::new(Quad::new(&Point3::default(), &Vec3::new(555.0, 0.0, 0.0), &Vec3::new(0.0, 0.0, 555.0), white.clone())));
     world.add(Box::new(Quad::new(&Point3::new(0.0, 0.0, 555.0), &Vec3::new(555.0, 0.0, 0.0), &Vec3::new(0.0, 555.0, 0.0), white.clone())));
@@ -417,6 +486,7 @@ fn cornell_smoke() -> Result<()> {
 
     //Render
     let mut camera = Camera::default();
+    camera.seed = seed;
     camera.aspect_ratio = 1.0;
     camera.image_width = 600;
     camera.samples_per_pixel = 200;
@@ -427,22 +497,324 @@ fn cornell_smoke() -> Result<()> {
     camera.look_at = Point3::new(278.0, 278.0, 0.0);
     camera.view_up = Vec3::new(0.0, 1.0, 0.0);
     camera.defocus_angle = 0.0;
-    camera.render(&mut world, &mut image)?;
+    camera.lights = lights;
+    let frame: FrameBuffer = camera.render(&world);
+    write_image(&frame, output_path, None)?;
+    write_image(&Denoiser::default().denoise(&frame), "./output/cornell_smoke_denoised.png", None)?;
+
+    Ok(())
+}
+
+fn perlin_turntable(seed: u64) -> Result<()> {
+    // Output
+    let output_pattern = "./output/perlin_turntable_####.png";
+
+    // World
+    seed_random(seed);
+    let mut world = HittableList::default();
+
+    let pertext = NoiseTexture::new(4.0);
+    let pertext_material = Box::new(Lambertian::new_from_texture(Box::new(pertext)));
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), None, 1000.0, pertext_material.clone())));
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, 2.0, 0.0), None, 2.0, pertext_material.clone())));
+
+    // One turn around the sphere in 48 frames, through a key every 6 frames
+    let frames: i32 = 48;
+
+    // A small sphere circling the other way, keyed in frames on the same timeline as the camera
+    let gold = Box::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.1));
+    let orbiter: Box<dyn Hittable> = Box::new(Sphere::new(&Point3::new(4.0, 0.7, 0.0), None, 0.7, gold));
+    world.add(Box::new(Keyframed::new(orbiter, &[
+        TransformKey { time: 0.0, offset: Vec3::default(), angle: 0.0 },
+        TransformKey { time: frames as f64, offset: Vec3::default(), angle: 360.0 },
+    ])));
+    let mut animation = CameraAnimation::new(Interpolation::CatmullRom);
+    for key in 0..=frames / 6 {
+        let angle: f64 = 2.0 * PI * (key * 6) as f64 / frames as f64;
+        animation.add_key(CameraKey {
+            time: (key * 6) as f64,
+            look_from: Point3::new(13.0 * angle.cos(), 2.0, 13.0 * angle.sin()),
+            look_at: Point3::new(0.0, 1.0, 0.0),
+            vertical_view_angle: 25.0,
+            focus_distance: 13.0,
+        });
+    }
+
+    //Render
+    let mut camera = Camera::default();
+    camera.seed = seed;
+    camera.aspect_ratio = 16.0 / 9.0;
+    camera.image_width = 400;
+    camera.samples_per_pixel = 50;
+    camera.max_depth = 50;
+    camera.background = Color::new(0.7, 0.8, 1.0);
+    camera.view_up = Vec3::new(0.0, 1.0, 0.0);
+    camera.animation = Some(animation);
+    // Open for half of each frame, like a 180 degree film shutter
+    camera.shutter_close = 0.5;
+    let mut result: Result<()> = Ok(());
+    camera.render_sequence(&world, 0..=frames - 1, |frame, image| {
+        if result.is_ok() {
+            result = write_image(image, &frame_path(output_pattern, frame), None);
+        }
+    });
+
+    result
+}
+
+fn cornell_motion(seed: u64) -> Result<()> {
+    // Output
+    let output_path = "./output/cornell_motion.png";
+
+    // World
+    seed_random(seed);
+    let mut world = HittableList::default();
+
+    let red = Box::new(Lambertian::new(&Color::new(0.65, 0.05, 0.05)));
+    let white = Box::new(Lambertian::new(&Color::new(0.73, 0.73, 0.73)));
+    let green = Box::new(Lambertian::new(&Color::new(0.12, 0.45, 0.15)));
+    let light = Box::new(DiffuseLight::new(&Color::new(15.0, 15.0, 15.0)));
+
+    world.add(Box::new(Quad::new(&Point3::new(555.0, 0.0, 0.0), &Vec3::new(0.0, 555.0, 0.0), &Vec3::new(0.0, 0.0, 555.0), green.clone())));
+    world.add(Box::new(Quad::new(&Point3::default(), &Vec3::new(0.0, 555.0, 0.0), &Vec3::new(0.0, 0.0, 555.0), red.clone())));
+    let mut lights = HittableList::default();
+    let light_quad: Box<dyn Hittable> = Box::new(Quad::new(&Point3::new(343.0, 554.0, 332.0), &Vec3::new(-130.0, 0.0, 0.0), &Vec3::new(0.0, 0.0, -105.0), light.clone()));
+    world.add(light_quad.clone());
+    lights.add(light_quad);
+    world.add(Box::new(Quad::new(&Point3::default(), &Vec3::new(555.0, 0.0, 0.0), &Vec3::new(0.0, 0.0, 555.0), white.clone())));
+    world.add(Box::new(Quad::new(&Point3::new(555.0, 555.0, 555.0), &Vec3::new(-555.0, 0.0, 0.0), &Vec3::new(0.0, 0.0, -555.0), white.clone())));
+    world.add(Box::new(Quad::new(&Point3::new(0.0, 0.0, 555.0), &Vec3::new(555.0, 0.0, 0.0), &Vec3::new(0.0, 555.0, 0.0), white.clone())));
+
+    // The tall box spins a quarter turn in place while the short one slides across the floor
+    let box1: Box<dyn Hittable> = make_box(&Point3::new(-82.5, 0.0, -82.5), &Point3::new(82.5, 330.0, 82.5), white.clone());
+    world.add(Box::new(Keyframed::new(box1, &[
+        TransformKey { time: 0.0, offset: Vec3::new(347.5, 0.0, 377.5), angle: 15.0 },
+        TransformKey { time: 1.0, offset: Vec3::new(347.5, 0.0, 377.5), angle: 105.0 },
+    ])));
+
+    let box2: Box<dyn Hittable> = make_box(&Point3::default(), &Point3::new(165.0, 165.0, 165.0), white.clone());
+    world.add(Box::new(Keyframed::new(box2, &[
+        TransformKey { time: 0.0, offset: Vec3::new(130.0, 0.0, 65.0), angle: -18.0 },
+        TransformKey { time: 0.5, offset: Vec3::new(100.0, 0.0, 65.0), angle: -18.0 },
+        TransformKey { time: 1.0, offset: Vec3::new(40.0, 0.0, 65.0), angle: -18.0 },
+    ])));
+
+    //Render
+    let mut camera = Camera::default();
+    camera.seed = seed;
+    camera.aspect_ratio = 1.0;
+    camera.image_width = 600;
+    camera.samples_per_pixel = 200;
+    camera.max_depth = 50;
+    camera.background = Color::default();
+    camera.vertical_view_angle = 40.0;
+    camera.look_from = Point3::new(278.0, 278.0, -800.0);
+    camera.look_at = Point3::new(278.0, 278.0, 0.0);
+    camera.view_up = Vec3::new(0.0, 1.0, 0.0);
+    camera.defocus_angle = 0.0;
+    camera.lights = lights;
+    let frame: FrameBuffer = camera.render(&world);
+    write_image(&frame, output_path, None)?;
+
+    Ok(())
+}
+
+fn environment_light(seed: u64) -> Result<()> {
+    // Output
+    let output_path = "./output/environment_light.png";
+
+    // World
+    seed_random(seed);
+    let mut world = HittableList::default();
+
+    let ground = Box::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), None, 1000.0, ground)));
+    let diffuse = Box::new(Lambertian::new(&Color::new(0.8, 0.3, 0.2)));
+    world.add(Box::new(Sphere::new(&Point3::new(-2.2, 1.0, 0.0), None, 1.0, diffuse)));
+    let glass = Box::new(Dielectric::new(1.5));
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 0.0), None, 1.0, glass)));
+    let metal = Box::new(Metal::new(Color::new(0.8, 0.8, 0.8), 0.0));
+    world.add(Box::new(Sphere::new(&Point3::new(2.2, 1.0, 0.0), None, 1.0, metal)));
+
+    //Render
+    let mut camera = Camera::default();
+    camera.seed = seed;
+    camera.aspect_ratio = 16.0 / 9.0;
+    camera.image_width = 400;
+    camera.samples_per_pixel = 100;
+    camera.max_depth = 50;
+    // Lit only by the captured sky, turned so the sun sits behind the camera
+    camera.environment = Some(Box::new(EnvironmentMap::new("environment.hdr", 180.0, 1.0)));
+    camera.vertical_view_angle = 30.0;
+    camera.look_from = Point3::new(0.0, 2.0, 12.0);
+    camera.look_at = Point3::new(0.0, 1.0, 0.0);
+    camera.view_up = Vec3::new(0.0, 1.0, 0.0);
+    camera.defocus_angle = 0.0;
+    let frame: FrameBuffer = camera.render(&world);
+    write_image(&frame, output_path, None)?;
+
+    Ok(())
+}
+
+fn daylight(seed: u64) -> Result<()> {
+    // Output
+    let output_path = "./output/daylight.png";
+
+    // World
+    seed_random(seed);
+    let mut world = HittableList::default();
+
+    let ground = Box::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), None, 1000.0, ground)));
+    let white = Box::new(Lambertian::new(&Color::new(0.73, 0.73, 0.73)));
+    let mut building: Box<dyn Hittable> = make_box(&Point3::default(), &Point3::new(2.0, 4.0, 2.0), white.clone());
+    building = Box::new(RotateY::new(building, 20.0));
+    building = Box::new(Translate::new(building, &Vec3::new(-2.5, 0.0, -1.0)));
+    world.add(building);
+    let metal = Box::new(Metal::new(Color::new(0.8, 0.8, 0.8), 0.0));
+    world.add(Box::new(Sphere::new(&Point3::new(1.5, 1.0, 0.0), None, 1.0, metal)));
+
+    // Late afternoon in Paris on the summer solstice, the sky is the only light
+    let sun: Vec3 = sun_direction(48.85, 2.35, 2024, 6, 21, 18.0, 2.0);
+    let sky = Sky::new(&sun, 3.0);
+
+    //Render
+    let mut camera = Camera::default();
+    camera.seed = seed;
+    camera.aspect_ratio = 16.0 / 9.0;
+    camera.image_width = 400;
+    camera.samples_per_pixel = 100;
+    camera.max_depth = 50;
+    camera.environment = Some(Box::new(sky));
+    camera.vertical_view_angle = 40.0;
+    camera.look_from = Point3::new(0.0, 2.0, 10.0);
+    camera.look_at = Point3::new(0.0, 1.5, 0.0);
+    camera.view_up = Vec3::new(0.0, 1.0, 0.0);
+    camera.defocus_angle = 0.0;
+    let frame: FrameBuffer = camera.render(&world);
+    write_image(&frame, output_path, None)?;
+
+    Ok(())
+}
+
+fn glossy_plates(seed: u64) -> Result<()> {
+    // Output
+    let output_path = "./output/glossy_plates.png";
+
+    // World
+    seed_random(seed);
+    let mut world = HittableList::default();
+    let mut lights = HittableList::default();
+
+    let floor = Box::new(Lambertian::new(&Color::new(0.4, 0.4, 0.4)));
+    world.add(Box::new(Quad::new(&Point3::new(-10.0, 0.0, -10.0), &Vec3::new(20.0, 0.0, 0.0), &Vec3::new(0.0, 0.0, 20.0), floor)));
+
+    // Plates from sharp to rough, each tilted to reflect the lights towards the camera
+    let look_from = Point3::new(0.0, 3.0, 8.0);
+    let lights_center = Point3::new(0.0, 4.0, -4.0);
+    for (index, fuzz) in [0.02, 0.06, 0.15, 0.4].into_iter().enumerate() {
+        let metal = Box::new(Metal::new(Color::new(0.8, 0.8, 0.8), fuzz));
+        let center = Point3::new(0.0, 0.4 + 0.4 * index as f64, 1.5 - 1.2 * index as f64);
+        let normal: Vec3 = unit_vector(&(unit_vector(&(look_from - center)) + unit_vector(&(lights_center - center))));
+        let across = Vec3::new(8.0, 0.0, 0.0);
+        let along: Vec3 = 0.9 * Vec3::new(0.0, normal.z(), -normal.y());
+        world.add(Box::new(Quad::new(&(center - 0.5 * across - 0.5 * along), &across, &along, metal)));
+    }
+
+    // Lights from small and bright to large and dim, all with the same power
+    for (index, radius) in [0.03, 0.1, 0.3, 0.9].into_iter().enumerate() {
+        let light = Box::new(DiffuseLight::new(&(0.4 / (radius * radius) * Color::new(1.0, 1.0, 1.0))));
+        let x: f64 = -3.75 + 2.5 * index as f64;
+        let sphere: Box<dyn Hittable> = Box::new(Sphere::new(&(lights_center + Vec3::new(x, 0.0, 0.0)), None, radius, light));
+        world.add(sphere.clone());
+        lights.add(sphere);
+    }
+
+    //Render
+    let mut camera = Camera::default();
+    camera.seed = seed;
+    camera.aspect_ratio = 16.0 / 9.0;
+    camera.image_width = 400;
+    camera.samples_per_pixel = 64;
+    camera.max_depth = 50;
+    camera.background = Color::default();
+    camera.vertical_view_angle = 40.0;
+    camera.look_from = look_from;
+    camera.look_at = Point3::new(0.0, 1.0, 0.0);
+    camera.view_up = Vec3::new(0.0, 1.0, 0.0);
+    camera.defocus_angle = 0.0;
+    camera.lights = lights;
+    let frame: FrameBuffer = camera.render(&world);
+    write_image(&frame, output_path, None)?;
+
+    Ok(())
+}
+
+fn delta_lights(seed: u64) -> Result<()> {
+    // Output
+    let output_path = "./output/delta_lights.png";
+
+    // World
+    seed_random(seed);
+    let mut world = HittableList::default();
+
+    let ground = Box::new(Lambertian::new(&Color::new(0.5, 0.5, 0.5)));
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, -1000.0, 0.0), None, 1000.0, ground)));
+    let diffuse = Box::new(Lambertian::new(&Color::new(0.8, 0.3, 0.2)));
+    world.add(Box::new(Sphere::new(&Point3::new(-2.2, 1.0, 0.0), None, 1.0, diffuse)));
+    let white = Box::new(Lambertian::new(&Color::new(0.73, 0.73, 0.73)));
+    world.add(Box::new(Sphere::new(&Point3::new(0.0, 1.0, 0.0), None, 1.0, white)));
+    let metal = Box::new(Metal::new(Color::new(0.8, 0.8, 0.8), 0.2));
+    world.add(Box::new(Sphere::new(&Point3::new(2.2, 1.0, 0.0), None, 1.0, metal)));
+
+    // A warm 100 W bulb to the left, a cool spot on the middle sphere and a dim low sun from behind
+    let delta_lights: Vec<Box<dyn Light>> = vec![
+        Box::new(PointLight::new_from_power(&Point3::new(-4.0, 3.0, 2.0), &Color::new(1.0, 0.8, 0.6), 100.0)),
+        Box::new(SpotLight::new(&Point3::new(1.0, 5.0, 3.0), &Vec3::new(-1.0, -4.0, -3.0), &Color::new(0.6, 0.8, 1.0), 40.0, 10.0, 20.0)),
+        Box::new(DirectionalLight::new(&Vec3::new(0.3, -0.4, 1.0), &Color::new(1.0, 0.9, 0.8), 0.3)),
+    ];
+
+    //Render
+    let mut camera = Camera::default();
+    camera.seed = seed;
+    camera.aspect_ratio = 16.0 / 9.0;
+    camera.image_width = 400;
+    camera.samples_per_pixel = 64;
+    camera.max_depth = 50;
+    camera.background = Color::new(0.02, 0.02, 0.03);
+    camera.vertical_view_angle = 30.0;
+    camera.look_from = Point3::new(0.0, 3.0, 12.0);
+    camera.look_at = Point3::new(0.0, 1.0, 0.0);
+    camera.view_up = Vec3::new(0.0, 1.0, 0.0);
+    camera.defocus_angle = 0.0;
+    camera.delta_lights = delta_lights;
+    let frame: FrameBuffer = camera.render(&world);
+    write_image(&frame, output_path, None)?;
 
     Ok(())
 }
 
 fn main() -> Result<()> {
-    let _ = create_dir_all("./output/")?;
+    create_dir_all("./output/")?;
+    // Ctrl-C stops the render, which then writes its checkpoint and the partial image, a second one exits
+    ctrlc::set_handler(interrupt).map_err(Error::other)?;
+
+    let seed: u64 = 0;
 
     match 8 {
-        1 => bouncing_spheres(),
-        2 => checkered_spheres(),
-        3 => earth(),
-        4 => perlin_spheres(),
-        5 => quads(),
-        6 => simple_light(),
-        7 => cornell_box(),
-        _ => cornell_smoke(),
+        1 => bouncing_spheres(seed),
+        2 => checkered_spheres(seed),
+        3 => earth(seed),
+        4 => perlin_spheres(seed),
+        5 => quads(seed),
+        6 => simple_light(seed),
+        7 => cornell_box(seed),
+        9 => perlin_turntable(seed),
+        10 => cornell_motion(seed),
+        11 => environment_light(seed),
+        12 => daylight(seed),
+        13 => glossy_plates(seed),
+        14 => delta_lights(seed),
+        _ => cornell_smoke(seed),
     }
 }
\ No newline at end of file
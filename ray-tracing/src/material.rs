@@ -2,17 +2,42 @@ use crate::color::Color;
 use crate::hittable::HitRecord;
 use crate::ray::Ray;
 use crate::texture::{SolidColor, Texture};
-use crate::utilities::random_double;
-use crate::vec3::{Point3, Vec3, dot, random_unit_vector, reflect, refract, unit_vector};
+use crate::sampler::Sampler;
+use crate::utilities::PI;
+use crate::vec3::{Point3, Vec3, dot, reflect, refract, sample_unit_vector, unit_vector};
 
-pub trait Material: MaterialClone {
-    fn scatter(&self, _ray_in: &Ray, _record: &HitRecord, _attenuation: &mut Color, _scattered: &mut Ray) -> bool {
+// What a scatter produced: the sampled ray and how it was drawn
+#[derive(Clone, Default)]
+pub struct ScatterRecord {
+    pub attenuation: Color,
+    pub scattered: Ray,
+    // Density of `scattered` over solid angle, unused for specular scattering
+    pub pdf: f64,
+    // Scattering into a single direction, which lights cannot be sampled for
+    pub specular: bool,
+}
+
+pub trait Material: MaterialClone + Send + Sync {
+    // Draws one 2D sample from the sampler per scatter so paths stay aligned across dimensions
+    fn scatter(&self, _ray_in: &Ray, _record: &HitRecord, _scatter_record: &mut ScatterRecord,
+            _sampler: &mut dyn Sampler) -> bool {
         false
     }
 
+    // Density of scattering into `scattered`, so attenuation times this is the reflected fraction
+    // for a direction chosen elsewhere, such as towards a light
+    fn scattering_pdf(&self, _ray_in: &Ray, _record: &HitRecord, _scattered: &Ray) -> f64 {
+        0.0
+    }
+
     fn emmited(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
         Color::default()
     }
+
+    // Surface colour for the albedo AOV, independent of lighting
+    fn albedo(&self, _record: &HitRecord) -> Color {
+        Color::default()
+    }
 }
 
 pub trait MaterialClone {
@@ -57,16 +82,29 @@ impl Default for Lambertian {
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, ray_in: &Ray, record: &HitRecord, attenuation: &mut Color, scattered: &mut Ray) 
-            -> bool {
-        let mut scatter_direction: Vec3 = record.normal + random_unit_vector();
+    fn scatter(&self, ray_in: &Ray, record: &HitRecord, scatter_record: &mut ScatterRecord,
+            sampler: &mut dyn Sampler) -> bool {
+        // Cosine weighted about the normal
+        let (u, v) = sampler.get_2d();
+        let mut scatter_direction: Vec3 = record.normal + sample_unit_vector(u, v);
         if scatter_direction.near_zero() {
             scatter_direction = record.normal
         }
-        *scattered = Ray::new(&record.point, &scatter_direction, Some(ray_in.time()));
-        *attenuation = self.texture.value(record.u, record.v, &record.point);
+        scatter_record.scattered = Ray::new(&record.point, &scatter_direction, Some(ray_in.time()));
+        scatter_record.attenuation = self.texture.value(record.u, record.v, &record.point);
+        scatter_record.pdf = self.scattering_pdf(ray_in, record, &scatter_record.scattered);
+        scatter_record.specular = false;
         true
     }
+
+    fn scattering_pdf(&self, _ray_in: &Ray, record: &HitRecord, scattered: &Ray) -> f64 {
+        let cosine: f64 = dot(&record.normal, &unit_vector(scattered.direction()));
+        cosine.max(0.0) / PI
+    }
+
+    fn albedo(&self, record: &HitRecord) -> Color {
+        self.texture.value(record.u, record.v, &record.point)
+    }
 }
 
 #[derive(Clone)]
@@ -85,13 +123,48 @@ impl Metal {
 }
 
 impl Material for Metal {
-    fn scatter(&self, ray_in: &Ray, record: &HitRecord, attenuation: &mut Color, scattered: &mut Ray) 
-            -> bool {
+    fn scatter(&self, ray_in: &Ray, record: &HitRecord, scatter_record: &mut ScatterRecord,
+            sampler: &mut dyn Sampler) -> bool {
         let mut reflected: Vec3 = reflect(ray_in.direction(), &record.normal);
-        reflected = unit_vector(&reflected) + (self.fuzz * random_unit_vector());
-        *scattered = Ray::new(&record.point, &reflected, Some(ray_in.time()));
-        *attenuation = self.albedo;
-        dot(scattered.direction(), &record.normal) > 0.0
+        let (u, v) = sampler.get_2d();
+        reflected = unit_vector(&reflected) + (self.fuzz * sample_unit_vector(u, v));
+        scatter_record.scattered = Ray::new(&record.point, &reflected, Some(ray_in.time()));
+        scatter_record.attenuation = self.albedo;
+        // Glossy reflections spread over a cone that lights can be sampled within
+        scatter_record.specular = self.fuzz <= 0.0;
+        scatter_record.pdf = if scatter_record.specular { 0.0 } else { self.scattering_pdf(ray_in, record, &scatter_record.scattered) };
+        dot(scatter_record.scattered.direction(), &record.normal) > 0.0
+    }
+
+    // The reflected direction is pushed to a uniform point on a sphere of radius `fuzz` around its tip,
+    // so a direction's density sums over where its line crosses that sphere
+    fn scattering_pdf(&self, ray_in: &Ray, record: &HitRecord, scattered: &Ray) -> f64 {
+        let direction: Vec3 = unit_vector(scattered.direction());
+        if self.fuzz <= 0.0 || dot(&direction, &record.normal) <= 0.0 {
+            return 0.0
+        }
+        let reflected: Vec3 = unit_vector(&reflect(ray_in.direction(), &record.normal));
+        let b: f64 = dot(&direction, &reflected);
+        let discriminant: f64 = b * b - 1.0 + self.fuzz * self.fuzz;
+        if discriminant <= 0.0 {
+            return 0.0
+        }
+        let mut pdf: f64 = 0.0;
+        for t in [b - discriminant.sqrt(), b + discriminant.sqrt()] {
+            if t <= 0.0 {
+                continue
+            }
+            let sphere_normal: Vec3 = (t * direction - reflected) / self.fuzz;
+            let cosine: f64 = dot(&direction, &sphere_normal).abs();
+            if cosine > 1e-8 {
+                pdf += t * t / (4.0 * PI * self.fuzz * self.fuzz * cosine);
+            }
+        }
+        pdf
+    }
+
+    fn albedo(&self, _record: &HitRecord) -> Color {
+        self.albedo
     }
 }
 
@@ -116,9 +189,10 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray_in: &Ray, record: &HitRecord, attenuation: &mut Color, scattered: &mut Ray) 
-            -> bool {
-        *attenuation = Color::new(1.0, 1.0, 1.0);
+    fn scatter(&self, ray_in: &Ray, record: &HitRecord, scatter_record: &mut ScatterRecord,
+            sampler: &mut dyn Sampler) -> bool {
+        scatter_record.attenuation = Color::new(1.0, 1.0, 1.0);
+        scatter_record.specular = true;
         let refraction_index: f64 = if record.front_face { 1.0 / self.refraction_index }
             else { self.refraction_index };
         let unit_direction: Vec3 = unit_vector(ray_in.direction());
@@ -126,14 +200,18 @@ impl Material for Dielectric {
         let sin_theta: f64 = (1.0 - cos_theta * cos_theta).sqrt();
         let cannot_refract: bool = refraction_index * sin_theta > 1.0;
         let direction: Vec3 = 
-            if cannot_refract || self.reflectance(cos_theta, refraction_index) > random_double(None) { 
+            if cannot_refract || self.reflectance(cos_theta, refraction_index) > sampler.get_2d().0 { 
                 reflect(&unit_direction, &record.normal) 
             } else { 
                 refract(&unit_direction, &record.normal, refraction_index) 
             };
-        *scattered = Ray::new(&record.point, &direction, Some(ray_in.time()));
+        scatter_record.scattered = Ray::new(&record.point, &direction, Some(ray_in.time()));
         true
     }
+
+    fn albedo(&self, _record: &HitRecord) -> Color {
+        Color::new(1.0, 1.0, 1.0)
+    }
 }
 
 #[derive(Clone)]
@@ -177,9 +255,21 @@ impl Isotropic {
 }
 
 impl Material for Isotropic {
-    fn scatter(&self, ray_in: &Ray, record: &HitRecord, attenuation: &mut Color, scattered: &mut Ray) -> bool {
-        *scattered = Ray::new(&record.point, &random_unit_vector(), Some(ray_in.time()));
-        *attenuation = self.texture.value(record.u, record.v, &record.point);
+    fn scatter(&self, ray_in: &Ray, record: &HitRecord, scatter_record: &mut ScatterRecord,
+            sampler: &mut dyn Sampler) -> bool {
+        let (u, v) = sampler.get_2d();
+        scatter_record.scattered = Ray::new(&record.point, &sample_unit_vector(u, v), Some(ray_in.time()));
+        scatter_record.attenuation = self.texture.value(record.u, record.v, &record.point);
+        scatter_record.pdf = 1.0 / (4.0 * PI);
+        scatter_record.specular = false;
         true
     }
+
+    fn scattering_pdf(&self, _ray_in: &Ray, _record: &HitRecord, _scattered: &Ray) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn albedo(&self, record: &HitRecord) -> Color {
+        self.texture.value(record.u, record.v, &record.point)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,36 @@
+use crate::vec3::{cross, unit_vector, Vec3};
+
+// Orthonormal basis with `w` along a given direction
+#[derive(Clone, Copy)]
+pub struct ONB {
+    axis: [Vec3; 3],
+}
+
+impl ONB {
+    pub fn new(n: &Vec3) -> Self {
+        let w: Vec3 = unit_vector(n);
+        let a: Vec3 = if w.x().abs() > 0.9 { Vec3::new(0.0, 1.0, 0.0) } else { Vec3::new(1.0, 0.0, 0.0) };
+        let v: Vec3 = unit_vector(&cross(&w, &a));
+        let u: Vec3 = cross(&w, &v);
+        Self {
+            axis: [u, v, w],
+        }
+    }
+
+    pub fn u(&self) -> Vec3 {
+        self.axis[0]
+    }
+
+    pub fn v(&self) -> Vec3 {
+        self.axis[1]
+    }
+
+    pub fn w(&self) -> Vec3 {
+        self.axis[2]
+    }
+
+    // From basis coordinates to world coordinates
+    pub fn transform(&self, v: &Vec3) -> Vec3 {
+        v.x() * self.axis[0] + v.y() * self.axis[1] + v.z() * self.axis[2]
+    }
+}
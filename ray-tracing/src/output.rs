@@ -0,0 +1,122 @@
+use std::fs::File;
+use std::io::{BufWriter, Error, ErrorKind, Result, Write};
+use std::path::Path;
+use image::{ImageBuffer, ImageEncoder, ImageFormat, Rgb};
+use image::codecs::pnm::{PnmEncoder, PnmSubtype, SampleEncoding};
+
+use crate::aov::Aov;
+use crate::color::{to_bytes, to_words};
+use crate::framebuffer::FrameBuffer;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    PpmAscii,
+    PpmBinary,
+    Png8,
+    Png16,
+    Hdr,
+    Pfm,
+    Exr,
+}
+
+impl OutputFormat {
+    // Only the compact encodings are guessed, ASCII P3 and 16 bit PNG have to be asked for
+    pub fn from_path(path: &str) -> Option<Self> {
+        let ext: String = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "ppm" => Some(Self::PpmBinary),
+            "png" => Some(Self::Png8),
+            "hdr" => Some(Self::Hdr),
+            "pfm" => Some(Self::Pfm),
+            "exr" => Some(Self::Exr),
+            _ => None,
+        }
+    }
+}
+
+// Writes the frame to `path`, using `format` or guessing it from the extension
+pub fn write_image(frame: &FrameBuffer, path: &str, format: Option<OutputFormat>) -> Result<()> {
+    let format: OutputFormat = match format.or_else(|| OutputFormat::from_path(path)) {
+        Some(f) => f,
+        None => return Err(Error::new(ErrorKind::InvalidInput, format!("Unknown output format for {path}"))),
+    };
+    let width = frame.width() as u32;
+    let height = frame.height() as u32;
+    match format {
+        OutputFormat::PpmAscii => frame.write_ppm(&mut BufWriter::new(File::create(path)?)),
+        OutputFormat::PpmBinary => {
+            let data: Vec<u8> = frame.pixels().iter().flat_map(|c| to_bytes(c, frame.display_transform())).collect();
+            PnmEncoder::new(BufWriter::new(File::create(path)?))
+                .with_subtype(PnmSubtype::Pixmap(SampleEncoding::Binary))
+                .write_image(&data, width, height, image::ExtendedColorType::Rgb8)
+                .map_err(Error::other)
+        },
+        OutputFormat::Png8 => {
+            let data: Vec<u8> = frame.pixels().iter().flat_map(|c| to_bytes(c, frame.display_transform())).collect();
+            let buffer: ImageBuffer<Rgb<u8>, Vec<u8>> = ImageBuffer::from_raw(width, height, data)
+                .expect("frame buffer size mismatch");
+            buffer.save_with_format(path, ImageFormat::Png).map_err(Error::other)
+        },
+        OutputFormat::Png16 => {
+            let data: Vec<u16> = frame.pixels().iter().flat_map(|c| to_words(c, frame.display_transform())).collect();
+            let buffer: ImageBuffer<Rgb<u16>, Vec<u16>> = ImageBuffer::from_raw(width, height, data)
+                .expect("frame buffer size mismatch");
+            buffer.save_with_format(path, ImageFormat::Png).map_err(Error::other)
+        },
+        OutputFormat::Hdr => float_buffer(frame).save_with_format(path, ImageFormat::Hdr).map_err(Error::other),
+        OutputFormat::Pfm => write_pfm(frame, &mut BufWriter::new(File::create(path)?)),
+        OutputFormat::Exr => float_buffer(frame).save_with_format(path, ImageFormat::OpenExr).map_err(Error::other),
+    }
+}
+
+// Fills the run of '#' in `pattern` with the zero padded frame number, render_####.png gives render_0042.png
+pub fn frame_path(pattern: &str, frame: i32) -> String {
+    let Some(start) = pattern.find('#') else {
+        let path = Path::new(pattern);
+        let stem: &str = path.file_stem().and_then(|s| s.to_str()).unwrap_or("frame");
+        let file_name: String = match path.extension().and_then(|s| s.to_str()) {
+            Some(extension) => format!("{stem}_{frame:04}.{extension}"),
+            None => format!("{stem}_{frame:04}"),
+        };
+        return path.with_file_name(file_name).to_string_lossy().into_owned()
+    };
+    let width: usize = pattern[start..].chars().take_while(|c| *c == '#').count();
+    format!("{}{:0width$}{}", &pattern[..start], frame, &pattern[start + width..])
+}
+
+// Writes every AOV next to `path`, e.g. render.exr gives render_depth.exr, render_normal.exr, ...
+pub fn write_aovs(frame: &FrameBuffer, path: &str, format: Option<OutputFormat>) -> Result<()> {
+    let path = Path::new(path);
+    let stem: &str = path.file_stem().and_then(|s| s.to_str()).unwrap_or("render");
+    let extension: &str = path.extension().and_then(|s| s.to_str()).unwrap_or("exr");
+    for aov in Aov::ALL {
+        let aov_path = path.with_file_name(format!("{stem}_{}.{extension}", aov.name()));
+        write_image(&frame.aov(aov), &aov_path.to_string_lossy(), format)?;
+    }
+    Ok(())
+}
+
+// Linear radiance, neither gamma encoded nor clamped
+fn float_buffer(frame: &FrameBuffer) -> ImageBuffer<Rgb<f32>, Vec<f32>> {
+    let data: Vec<f32> = frame.pixels().iter()
+        .flat_map(|c| [c.x() as f32, c.y() as f32, c.z() as f32])
+        .collect();
+    ImageBuffer::from_raw(frame.width() as u32, frame.height() as u32, data)
+        .expect("frame buffer size mismatch")
+}
+
+// Portable float map: little endian (negative scale) and stored bottom row first
+fn write_pfm(frame: &FrameBuffer, out: &mut impl Write) -> Result<()> {
+    writeln!(out, "PF")?;
+    writeln!(out, "{} {}", frame.width(), frame.height())?;
+    writeln!(out, "-1.0")?;
+    for j in (0..frame.height()).rev() {
+        for i in 0..frame.width() {
+            let pixel_color = frame.pixel(i, j);
+            for c in 0..3 {
+                out.write_all(&(pixel_color[c] as f32).to_le_bytes())?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -33,10 +33,10 @@ impl Perlin {
         let k: usize = (p.z().floor() + self.point_count as f64 / 2.0) as usize;
 
         let mut c: Vec<Vec<Vec<Vec3>>> = vec![vec![vec![Vec3::default(); 2]; 2]; 2];
-        for di in 0..2 {
-            for dj in 0..2 {
-                for dk in 0..2 {
-                    c[di][dj][dk] = self.rand_vec[
+        for (di, plane) in c.iter_mut().enumerate() {
+            for (dj, row) in plane.iter_mut().enumerate() {
+                for (dk, corner) in row.iter_mut().enumerate() {
+                    *corner = self.rand_vec[
                         self.perm_x[(i + di) & 255]
                         ^ self.perm_y[(j + dj) & 255]
                         ^ self.perm_z[(k + dk) & 255]
@@ -60,7 +60,7 @@ impl Perlin {
         accum.abs()
     }
 
-    fn perlin_generate_perm(&mut self, axis: i32) -> () {
+    fn perlin_generate_perm(&mut self, axis: i32) {
         let mut perm: Vec<usize> = Vec::from_iter(0..self.point_count);
         perm = self.permute(&mut perm);
         match axis {
@@ -70,14 +70,12 @@ impl Perlin {
         }
     }
 
-    fn permute(&self, perm: &mut Vec<usize>) -> Vec<usize> {
+    fn permute(&self, perm: &mut [usize]) -> Vec<usize> {
         for i in (0..self.point_count).rev() {
             let target: usize = random_int(0, i as i32) as usize;
-            let tmp: usize = perm[i];
-            perm[i] = perm[target];
-            perm[target] = tmp;
+            perm.swap(i, target);
         }
-        perm.clone()
+        perm.to_vec()
     }
 
     fn perlin_interp(&self, c: Vec<Vec<Vec<Vec3>>>, u: f64, v: f64, w: f64) -> f64 {
@@ -86,14 +84,14 @@ impl Perlin {
         let ww: f64 = w * w * (3.0 - 2.0 * w);
         let mut accum: f64 = 0.0;
 
-        for i in 0..2 {
-            for j in 0..2 {
-                for k in 0..2 {
+        for (i, plane) in c.iter().enumerate() {
+            for (j, row) in plane.iter().enumerate() {
+                for (k, corner) in row.iter().enumerate() {
                     let weight_vector = Vec3::new(u - i as f64, v - j as f64, w - k as f64);
                     accum += (i as f64 * uu + (1.0 - i as f64) * (1.0 - uu))
                     * (j as f64 * vv + (1.0 - j as f64) * (1.0 - vv))
                     * (k as f64 * ww + (1.0 - k as f64) * (1.0 - ww))
-                    * dot(&c[i][j][k], &weight_vector);
+                    * dot(corner, &weight_vector);
                 }
             }
         }
@@ -1,10 +1,12 @@
 use crate::aabb::AABB;
+use crate::aov::next_object_id;
 use crate::color::Color;
 use crate::hittable::{HitRecord, Hittable};
 use crate::hittable_list::HittableList;
 use crate::interval::Interval;
 use crate::material::{Lambertian, Material};
 use crate::ray::Ray;
+use crate::utilities::INFINITY;
 use crate::vec3::{cross, dot, unit_vector, Point3, Vec3};
 
 #[derive(Clone)]
@@ -17,6 +19,8 @@ pub struct Quad {
     bbox: AABB,
     normal: Vec3,
     d: f64,
+    area: f64,
+    id: u32,
 }
 
 impl Quad {
@@ -31,13 +35,15 @@ impl Quad {
             material,
             bbox: AABB::default(),
             normal,
-            d: dot(&normal, q)
+            d: dot(&normal, q),
+            area: n.length(),
+            id: next_object_id(),
         };
         result.set_bounding_box();
         result
     }
 
-    pub fn set_bounding_box(&mut self) -> () {
+    pub fn set_bounding_box(&mut self) {
         let bbox_diag1 = AABB::new_from_points(&self.q, &(self.q + self.u + self.v));
         let bbox_diag2 = AABB::new_from_points(&(self.q + self.u), &(self.q + self.v));
         self.bbox = AABB::new_from_children(&bbox_diag1, &bbox_diag2);
@@ -80,6 +86,7 @@ impl Hittable for Quad {
         record.time = t;
         record.point = intersection;
         record.material = self.material.clone();
+        record.object_id = self.id;
         record.set_face_normal(ray, &self.normal);
         true
     }
@@ -87,6 +94,21 @@ impl Hittable for Quad {
     fn bounding_box(&self) -> AABB {
         self.bbox
     }
+
+    // Uniform over the area, seen from either side
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        let mut record = HitRecord::default();
+        if !self.hit(&Ray::new(origin, direction, Some(time)), &Interval::new(0.001, INFINITY), &mut record) {
+            return 0.0
+        }
+        let distance_squared: f64 = record.time * record.time * direction.length_squared();
+        let cosine: f64 = (dot(direction, &record.normal) / direction.length()).abs();
+        distance_squared / (cosine * self.area)
+    }
+
+    fn sample_direction(&self, origin: &Point3, _time: f64, u: f64, v: f64) -> Vec3 {
+        self.q + (u * self.u) + (v * self.v) - *origin
+    }
 }
 
 pub fn make_box(a: &Point3, b: &Point3, material: Box<dyn Material>) -> Box<HittableList> {
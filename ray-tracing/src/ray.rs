@@ -9,10 +9,7 @@ pub struct Ray {
 
 impl Ray {
     pub fn new(origin: &Point3, direction: &Vec3, time: Option<f64>) -> Self {
-        let t = match time {
-            Some(tm) => tm,
-            None => 0.0,
-        };
+        let t = time.unwrap_or(0.0);
         Self { 
             origin: *origin,
             direction: *direction,
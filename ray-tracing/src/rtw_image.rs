@@ -34,40 +34,39 @@ impl RTWImage {
         let path = Path::new(filename);
 
         // Handle Radiance HDR files (.hdr) which provide f32 samples
-        if let Some(ext) = path.extension().and_then(|s| s.to_str()) {
-            if ext.eq_ignore_ascii_case("hdr") {
-                let file = match File::open(path) {
-                    Ok(f) => f,
-                    Err(_) => return false,
-                };
-                let reader = BufReader::new(file);
-                let decoder = match HdrDecoder::new(reader) {
-                    Ok(d) => d,
-                    Err(_) => return false,
-                };
-                let (w, h) = decoder.dimensions();
-                // total floats (RGB) and total bytes
-                let total_floats = (w as usize) * (h as usize) * 3;
-                let mut raw_bytes = vec![0u8; total_floats * std::mem::size_of::<f32>()];
-                if decoder.read_image(&mut raw_bytes).is_err() {
-                    return false;
-                }
-
-                // convert bytes -> f32 (native endian)
-                self.fdata = Vec::with_capacity(total_floats);
-                for i in 0..total_floats {
-                    let off = i * 4;
-                    let b = [raw_bytes[off], raw_bytes[off + 1], raw_bytes[off + 2], raw_bytes[off + 3]];
-                    self.fdata.push(f32::from_ne_bytes(b));
-                }
-
-                self.image_width = w as i32;
-                self.image_height = h as i32;
-                self.bytes_per_pixel = 3;
-                self.bytes_per_scanline = self.image_width * self.bytes_per_pixel;
-                self.convert_to_bytes();
-                return true;
+        if let Some(ext) = path.extension().and_then(|s| s.to_str())
+            && ext.eq_ignore_ascii_case("hdr") {
+            let file = match File::open(path) {
+                Ok(f) => f,
+                Err(_) => return false,
+            };
+            let reader = BufReader::new(file);
+            let decoder = match HdrDecoder::new(reader) {
+                Ok(d) => d,
+                Err(_) => return false,
+            };
+            let (w, h) = decoder.dimensions();
+            // total floats (RGB) and total bytes
+            let total_floats = (w as usize) * (h as usize) * 3;
+            let mut raw_bytes = vec![0u8; total_floats * std::mem::size_of::<f32>()];
+            if decoder.read_image(&mut raw_bytes).is_err() {
+                return false;
             }
+
+            // convert bytes -> f32 (native endian)
+            self.fdata = Vec::with_capacity(total_floats);
+            for i in 0..total_floats {
+                let off = i * 4;
+                let b = [raw_bytes[off], raw_bytes[off + 1], raw_bytes[off + 2], raw_bytes[off + 3]];
+                self.fdata.push(f32::from_ne_bytes(b));
+            }
+
+            self.image_width = w as i32;
+            self.image_height = h as i32;
+            self.bytes_per_pixel = 3;
+            self.bytes_per_scanline = self.image_width * self.bytes_per_pixel;
+            self.convert_to_bytes();
+            return true;
         }
 
         // Fallback: use ImageReader for other formats and convert to f32 [0..1]
@@ -129,7 +128,16 @@ impl RTWImage {
         let x: i32 = self.clamp(x, 0, self.image_width);
         let y: i32 = self.clamp(y, 0, self.image_height);
 
-        return (y * self.bytes_per_scanline + x * self.bytes_per_pixel) as usize;
+        (y * self.bytes_per_scanline + x * self.bytes_per_pixel) as usize
+    }
+
+    // Linear RGB as loaded, HDR values above 1 are kept
+    pub fn pixel_float(&self, x: i32, y: i32) -> [f32; 3] {
+        if self.fdata.is_empty() {
+            return [0.0; 3]
+        }
+        let index: usize = self.pixel_data(x, y);
+        [self.fdata[index], self.fdata[index + 1], self.fdata[index + 2]]
     }
 
     fn clamp(&self, x: i32, low: i32, high: i32) -> i32 {
@@ -150,7 +158,7 @@ impl RTWImage {
         }
     }
 
-    fn convert_to_bytes(&mut self) -> () {
+    fn convert_to_bytes(&mut self) {
         let total_bytes = (self.image_width * self.image_height * self.bytes_per_pixel) as usize;
         self.bdata = vec![0; total_bytes];
         for i in 0..total_bytes {
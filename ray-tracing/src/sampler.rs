@@ -0,0 +1,339 @@
+use crate::utilities::{hash_seed, random_double};
+
+// Dimensions are consumed in a fixed order for every camera sample:
+// pixel (2D), lens (2D), time (1D), then per bounce the material's scatter dimensions, followed by
+// the light sample (2D) when a diffuse bounce samples the lights, the environment sample (2D) when
+// it samples an environment and the Russian roulette (1D) once the path is deep enough.
+pub trait Sampler {
+    fn start_pixel_sample(&mut self, x: i32, y: i32, sample_index: i32);
+    fn get_1d(&mut self) -> f64;
+    fn get_2d(&mut self) -> (f64, f64);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SamplerKind {
+    Independent,
+    Stratified,
+    Halton,
+    Sobol,
+}
+
+impl SamplerKind {
+    // `frame` decorrelates the frames of an animation, which would otherwise share their noise
+    pub fn create(&self, samples_per_pixel: i32, seed: u64, frame: i32) -> Box<dyn Sampler> {
+        match self {
+            Self::Independent => Box::new(IndependentSampler),
+            Self::Stratified => Box::new(StratifiedSampler::new(samples_per_pixel, seed, frame)),
+            Self::Halton => Box::new(HaltonSampler::new(seed, frame)),
+            Self::Sobol => Box::new(SobolSampler::new(seed, frame)),
+        }
+    }
+}
+
+// Draws straight from the per-sample random stream
+pub struct IndependentSampler;
+
+impl Sampler for IndependentSampler {
+    fn start_pixel_sample(&mut self, _x: i32, _y: i32, _sample_index: i32) {}
+
+    fn get_1d(&mut self) -> f64 {
+        random_double(None)
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (random_double(None), random_double(None))
+    }
+}
+
+// Jittered strata, visited in a different shuffled order for each pixel and dimension
+pub struct StratifiedSampler {
+    samples_per_pixel: u32,
+    strata_x: u32,
+    strata_y: u32,
+    seed: u64,
+    frame: u64,
+    pixel_seed: u64,
+    sample_index: u32,
+    dimension: u64,
+}
+
+impl StratifiedSampler {
+    pub fn new(samples_per_pixel: i32, seed: u64, frame: i32) -> Self {
+        let samples_per_pixel = samples_per_pixel.max(1) as u32;
+        let strata_x = (samples_per_pixel as f64).sqrt().floor().max(1.0) as u32;
+        Self {
+            samples_per_pixel,
+            strata_x,
+            strata_y: samples_per_pixel / strata_x,
+            seed,
+            frame: frame as u64,
+            pixel_seed: 0,
+            sample_index: 0,
+            dimension: 0,
+        }
+    }
+
+    fn next_dimension_seed(&mut self) -> u64 {
+        self.dimension += 1;
+        hash_seed(&[self.pixel_seed, self.dimension])
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_pixel_sample(&mut self, x: i32, y: i32, sample_index: i32) {
+        self.pixel_seed = hash_seed(&[self.seed, self.frame, x as u64, y as u64]);
+        self.sample_index = sample_index as u32;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let seed: u64 = self.next_dimension_seed();
+        let count: u32 = self.samples_per_pixel;
+        let stratum: u32 = permutation_element(self.sample_index % count, count, seed as u32);
+        (stratum as f64 + random_double(None)) / count as f64
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let seed: u64 = self.next_dimension_seed();
+        let count: u32 = self.strata_x * self.strata_y;
+        let stratum: u32 = permutation_element(self.sample_index % count, count, seed as u32);
+        (
+            ((stratum % self.strata_x) as f64 + random_double(None)) / self.strata_x as f64,
+            ((stratum / self.strata_x) as f64 + random_double(None)) / self.strata_y as f64,
+        )
+    }
+}
+
+const HALTON_PRIMES: [u32; 24] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89,
+];
+
+// Radical inverses in prime bases, decorrelated between pixels by a random toroidal shift
+pub struct HaltonSampler {
+    seed: u64,
+    frame: u64,
+    pixel_seed: u64,
+    sample_index: u64,
+    dimension: usize,
+}
+
+impl HaltonSampler {
+    pub fn new(seed: u64, frame: i32) -> Self {
+        Self {
+            seed,
+            frame: frame as u64,
+            pixel_seed: 0,
+            sample_index: 0,
+            dimension: 0,
+        }
+    }
+
+    fn next(&mut self) -> f64 {
+        let dimension: usize = self.dimension;
+        self.dimension += 1;
+        if dimension >= HALTON_PRIMES.len() {
+            return random_double(None)
+        }
+        let value: f64 = radical_inverse(HALTON_PRIMES[dimension], self.sample_index);
+        let shift: f64 = to_unit(hash_seed(&[self.pixel_seed, dimension as u64]));
+        (value + shift).fract()
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_pixel_sample(&mut self, x: i32, y: i32, sample_index: i32) {
+        self.pixel_seed = hash_seed(&[self.seed, self.frame, x as u64, y as u64]);
+        // Skip the first point, which is zero in every dimension
+        self.sample_index = sample_index as u64 + 1;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        self.next()
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.next(), self.next())
+    }
+}
+
+// Primitive polynomials (degree, coefficients) and initial direction numbers from Joe and Kuo
+const SOBOL_POLYNOMIALS: [(u32, u32, &[u32]); 15] = [
+    (1, 0, &[1]),
+    (2, 1, &[1, 3]),
+    (3, 1, &[1, 3, 1]),
+    (3, 2, &[1, 1, 1]),
+    (4, 1, &[1, 1, 3, 3]),
+    (4, 4, &[1, 3, 5, 13]),
+    (5, 2, &[1, 1, 5, 5, 17]),
+    (5, 4, &[1, 1, 5, 5, 5]),
+    (5, 7, &[1, 1, 7, 11, 19]),
+    (5, 11, &[1, 1, 5, 1, 1]),
+    (5, 13, &[1, 1, 1, 3, 11]),
+    (5, 14, &[1, 3, 5, 5, 31]),
+    (6, 1, &[1, 3, 3, 9, 7, 49]),
+    (6, 13, &[1, 1, 1, 15, 21, 21]),
+    (6, 16, &[1, 3, 1, 13, 27, 49]),
+];
+
+// Owen-scrambled Sobol points, with the index shuffled per pixel and dimension pair
+pub struct SobolSampler {
+    directions: Vec<[u32; 32]>,
+    seed: u64,
+    frame: u64,
+    pixel_seed: u64,
+    sample_index: u32,
+    dimension: usize,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64, frame: i32) -> Self {
+        let mut directions: Vec<[u32; 32]> = Vec::with_capacity(SOBOL_POLYNOMIALS.len() + 1);
+        // The first dimension is the van der Corput sequence in base 2
+        let mut first = [0u32; 32];
+        for (bit, direction) in first.iter_mut().enumerate() {
+            *direction = 1 << (31 - bit);
+        }
+        directions.push(first);
+        for (degree, coefficients, initial) in SOBOL_POLYNOMIALS {
+            directions.push(sobol_directions(degree, coefficients, initial));
+        }
+        Self {
+            directions,
+            seed,
+            frame: frame as u64,
+            pixel_seed: 0,
+            sample_index: 0,
+            dimension: 0,
+        }
+    }
+
+    fn sample(&self, index: u32, dimension: usize) -> u32 {
+        let mut result: u32 = 0;
+        let mut index: u32 = index;
+        let mut bit: usize = 0;
+        while index != 0 {
+            if index & 1 == 1 {
+                result ^= self.directions[dimension][bit];
+            }
+            index >>= 1;
+            bit += 1;
+        }
+        result
+    }
+
+    fn next(&mut self, pair_seed: u32) -> f64 {
+        let dimension: usize = self.dimension;
+        self.dimension += 1;
+        if dimension >= self.directions.len() {
+            return random_double(None)
+        }
+        let index: u32 = nested_uniform_scramble(self.sample_index, pair_seed);
+        let scramble_seed = hash_seed(&[self.pixel_seed, dimension as u64]) as u32;
+        let value: u32 = nested_uniform_scramble(self.sample(index, dimension), scramble_seed);
+        (value as f64 / 4294967296.0).min(ONE_MINUS_EPSILON)
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_pixel_sample(&mut self, x: i32, y: i32, sample_index: i32) {
+        self.pixel_seed = hash_seed(&[self.seed, self.frame, x as u64, y as u64]);
+        self.sample_index = sample_index as u32;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let pair_seed = hash_seed(&[self.pixel_seed, self.dimension as u64, 1]) as u32;
+        self.next(pair_seed)
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        // Both dimensions share the shuffled index so the pair keeps its stratification
+        let pair_seed = hash_seed(&[self.pixel_seed, self.dimension as u64, 2]) as u32;
+        (self.next(pair_seed), self.next(pair_seed))
+    }
+}
+
+const ONE_MINUS_EPSILON: f64 = 1.0 - f64::EPSILON;
+
+fn to_unit(hash: u64) -> f64 {
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
+
+fn radical_inverse(base: u32, mut index: u64) -> f64 {
+    let inverse_base: f64 = 1.0 / base as f64;
+    let mut inverse_base_power: f64 = 1.0;
+    let mut reversed_digits: u64 = 0;
+    while index > 0 {
+        let next: u64 = index / base as u64;
+        let digit: u64 = index - next * base as u64;
+        reversed_digits = reversed_digits * base as u64 + digit;
+        inverse_base_power *= inverse_base;
+        index = next;
+    }
+    (reversed_digits as f64 * inverse_base_power).min(ONE_MINUS_EPSILON)
+}
+
+fn sobol_directions(degree: u32, coefficients: u32, initial: &[u32]) -> [u32; 32] {
+    let s = degree as usize;
+    let mut directions = [0u32; 32];
+    for i in 0..32 {
+        if i < s {
+            directions[i] = initial[i] << (31 - i);
+            continue
+        }
+        let mut value: u32 = directions[i - s] ^ (directions[i - s] >> s);
+        for k in 1..s {
+            if (coefficients >> (s - 1 - k)) & 1 == 1 {
+                value ^= directions[i - k];
+            }
+        }
+        directions[i] = value;
+    }
+    directions
+}
+
+// Laine-Karras hash, an inexpensive stand-in for Owen scrambling when applied to reversed bits
+fn nested_uniform_scramble(value: u32, seed: u32) -> u32 {
+    let mut x: u32 = value.reverse_bits();
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x.reverse_bits()
+}
+
+// Kensler's hashed permutation: element `i` of a random permutation of 0..n
+fn permutation_element(mut i: u32, n: u32, seed: u32) -> u32 {
+    let mut w: u32 = n.wrapping_sub(1);
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= seed;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= seed >> 16;
+        i ^= (i & w) >> 4;
+        i ^= seed >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= seed >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | seed >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < n {
+            break
+        }
+    }
+    (i.wrapping_add(seed)) % n
+}
@@ -0,0 +1,216 @@
+use crate::color::Color;
+use crate::environment::Environment;
+use crate::onb::ONB;
+use crate::utilities::{degrees_to_radians, PI};
+use crate::vec3::{dot, unit_vector, Vec3};
+
+// Sky luminance comes out in kcd/m^2, this brings a clear day to roughly 1
+const KILOCANDELA_SCALE: f64 = 0.1;
+// Luminance of the sun above the atmosphere, in kcd/m^2
+const SUN_LUMINANCE: f64 = 1.6e6;
+// Sun elevation in degrees at which the sky has faded to black, the end of civil twilight
+const TWILIGHT_ELEVATION: f64 = -6.0;
+
+// Clear daylight sky after Preetham, Shirley and Smits, with a sun disk that is sampled directly
+#[derive(Clone)]
+pub struct Sky {
+    sun_direction: Vec3,
+    turbidity: f64,
+    // Perez coefficients A to E for luminance Y and chromaticities x and y
+    perez_y: [f64; 5],
+    perez_x: [f64; 5],
+    perez_chroma_y: [f64; 5],
+    // Zenith value divided by the Perez function there, for Y, x and y
+    zenith: [f64; 3],
+    // Dims the sky as the sun sinks from the horizon to the end of twilight
+    twilight: f64,
+    sun_color: Color,
+    // Angular radius of the sun in degrees
+    pub sun_size: f64,
+    pub intensity: f64,
+    // Reflectance of the ground below the horizon, which sees the horizon sky
+    pub ground: Color,
+}
+
+impl Sky {
+    // `turbidity` ranges from 2 (very clear) to about 10 (hazy)
+    pub fn new(sun_direction: &Vec3, turbidity: f64) -> Self {
+        let sun_direction: Vec3 = unit_vector(sun_direction);
+        let t: f64 = turbidity;
+        let perez_y = [0.1787 * t - 1.4630, -0.3554 * t + 0.4275, -0.0227 * t + 5.3251, 0.1206 * t - 2.5771, -0.0670 * t + 0.3703];
+        let perez_x = [-0.0193 * t - 0.2592, -0.0665 * t + 0.0008, -0.0004 * t + 0.2125, -0.0641 * t - 0.8989, -0.0033 * t + 0.0452];
+        let perez_chroma_y = [-0.0167 * t - 0.2608, -0.0950 * t + 0.0092, -0.0079 * t + 0.2102, -0.0441 * t - 1.6537, -0.0109 * t + 0.0529];
+
+        // The model is only defined for the sun above the horizon, below it the sky at sunset fades out
+        let theta_sun: f64 = sun_direction.y().clamp(0.0, 1.0).acos();
+        let elevation: f64 = sun_direction.y().clamp(-1.0, 1.0).asin().to_degrees();
+        let twilight: f64 = (1.0 - elevation / TWILIGHT_ELEVATION).clamp(0.0, 1.0);
+        let chi: f64 = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_sun);
+        let zenith_y: f64 = ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192).max(0.0);
+        let thetas = [theta_sun.powi(3), theta_sun.powi(2), theta_sun, 1.0];
+        let turbidities = [t * t, t, 1.0];
+        let zenith_x: f64 = chromaticity(&turbidities, &thetas, &[
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let zenith_chroma_y: f64 = chromaticity(&turbidities, &thetas, &[
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        Self {
+            sun_direction,
+            turbidity,
+            perez_y,
+            perez_x,
+            perez_chroma_y,
+            zenith: [
+                zenith_y / perez(&perez_y, 0.0, theta_sun),
+                zenith_x / perez(&perez_x, 0.0, theta_sun),
+                zenith_chroma_y / perez(&perez_chroma_y, 0.0, theta_sun),
+            ],
+            twilight,
+            sun_color: sun_transmittance(theta_sun, turbidity),
+            sun_size: 0.265,
+            intensity: 1.0,
+            ground: Color::new(0.3, 0.3, 0.3),
+        }
+    }
+
+    pub fn sun_direction(&self) -> Vec3 {
+        self.sun_direction
+    }
+
+    pub fn turbidity(&self) -> f64 {
+        self.turbidity
+    }
+
+    fn sun_cos_theta_max(&self) -> f64 {
+        degrees_to_radians(self.sun_size).cos()
+    }
+
+    fn sky_color(&self, direction: &Vec3) -> Color {
+        // Clamped just above the horizon, where the Perez function blows up
+        let theta: f64 = direction.y().clamp(0.001, 1.0).acos();
+        let gamma: f64 = dot(direction, &self.sun_direction).clamp(-1.0, 1.0).acos();
+        let luminance: f64 = self.zenith[0] * perez(&self.perez_y, theta, gamma);
+        let x: f64 = self.zenith[1] * perez(&self.perez_x, theta, gamma);
+        let y: f64 = self.zenith[2] * perez(&self.perez_chroma_y, theta, gamma);
+        self.twilight * KILOCANDELA_SCALE * xyy_to_rgb(x, y, luminance)
+    }
+}
+
+impl Environment for Sky {
+    fn value(&self, direction: &Vec3) -> Color {
+        let direction: Vec3 = unit_vector(direction);
+        if direction.y() < 0.0 {
+            let horizontal = Vec3::new(direction.x(), 0.0, direction.z());
+            let horizon: Vec3 = if horizontal.near_zero() { Vec3::new(1.0, 0.0, 0.0) } else { unit_vector(&horizontal) };
+            return self.intensity * self.ground * self.sky_color(&horizon)
+        }
+        let mut color: Color = self.sky_color(&direction);
+        if dot(&direction, &self.sun_direction) >= self.sun_cos_theta_max() {
+            color += KILOCANDELA_SCALE * SUN_LUMINANCE * self.sun_color;
+        }
+        self.intensity * color
+    }
+
+    // Uniform over the cone of the sun disk, far too small for scattered rays to find on their own
+    fn pdf_value(&self, direction: &Vec3) -> f64 {
+        let cos_theta_max: f64 = self.sun_cos_theta_max();
+        // Below the horizon the sun is hidden by the ground, so it is not worth sampling
+        if self.sun_direction.y() < 0.0 || cos_theta_max >= 1.0 || dot(&unit_vector(direction), &self.sun_direction) < cos_theta_max {
+            return 0.0
+        }
+        1.0 / (2.0 * PI * (1.0 - cos_theta_max))
+    }
+
+    fn sample_direction(&self, u: f64, v: f64) -> Vec3 {
+        let z: f64 = 1.0 + v * (self.sun_cos_theta_max() - 1.0);
+        let phi: f64 = 2.0 * PI * u;
+        let r: f64 = (1.0 - z * z).max(0.0).sqrt();
+        ONB::new(&self.sun_direction).transform(&Vec3::new(phi.cos() * r, phi.sin() * r, z))
+    }
+}
+
+// Direction towards the sun, azimuth in degrees clockwise from north (-z) towards east (+x)
+pub fn sun_direction_from_angles(elevation: f64, azimuth: f64) -> Vec3 {
+    let elevation: f64 = degrees_to_radians(elevation);
+    let azimuth: f64 = degrees_to_radians(azimuth);
+    Vec3::new(elevation.cos() * azimuth.sin(), elevation.sin(), -elevation.cos() * azimuth.cos())
+}
+
+// Direction towards the sun from a place on earth, in the frame of `sun_direction_from_angles`.
+// Latitude is positive north and longitude positive east, in degrees; `hour` is the local clock time
+// and `utc_offset` its difference from UTC in hours. Uses the NOAA approximation, good to a fraction
+// of a degree.
+pub fn sun_direction(latitude: f64, longitude: f64, year: i32, month: u32, day: u32, hour: f64, utc_offset: f64) -> Vec3 {
+    let leap: bool = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    let days_before_month: [u32; 12] = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+    let month_index: usize = (month.clamp(1, 12) - 1) as usize;
+    let day_of_year: u32 = days_before_month[month_index] + day + if leap && month > 2 { 1 } else { 0 };
+    let days_in_year: f64 = if leap { 366.0 } else { 365.0 };
+
+    // Fractional year in radians
+    let g: f64 = 2.0 * PI / days_in_year * (day_of_year as f64 - 1.0 + (hour - utc_offset - 12.0) / 24.0);
+    let equation_of_time: f64 = 229.18 * (0.000075 + 0.001868 * g.cos() - 0.032077 * g.sin()
+        - 0.014615 * (2.0 * g).cos() - 0.040849 * (2.0 * g).sin());
+    let declination: f64 = 0.006918 - 0.399912 * g.cos() + 0.070257 * g.sin() - 0.006758 * (2.0 * g).cos()
+        + 0.000907 * (2.0 * g).sin() - 0.002697 * (3.0 * g).cos() + 0.00148 * (3.0 * g).sin();
+
+    // Solar time in minutes, the sun is due south (or north) at noon
+    let solar_time: f64 = hour * 60.0 + equation_of_time + 4.0 * longitude - 60.0 * utc_offset;
+    let hour_angle: f64 = degrees_to_radians(solar_time / 4.0 - 180.0);
+    let latitude: f64 = degrees_to_radians(latitude);
+
+    let east: f64 = -declination.cos() * hour_angle.sin();
+    let north: f64 = latitude.cos() * declination.sin() - latitude.sin() * declination.cos() * hour_angle.cos();
+    let up: f64 = latitude.sin() * declination.sin() + latitude.cos() * declination.cos() * hour_angle.cos();
+    Vec3::new(east, up, -north)
+}
+
+fn perez(coefficients: &[f64; 5], theta: f64, gamma: f64) -> f64 {
+    let [a, b, c, d, e] = *coefficients;
+    (1.0 + a * (b / theta.cos()).exp()) * (1.0 + c * (d * gamma).exp() + e * gamma.cos() * gamma.cos())
+}
+
+fn chromaticity(turbidities: &[f64; 3], thetas: &[f64; 4], matrix: &[[f64; 4]; 3]) -> f64 {
+    let mut result: f64 = 0.0;
+    for (row, turbidity) in matrix.iter().zip(turbidities) {
+        for (value, theta) in row.iter().zip(thetas) {
+            result += turbidity * value * theta;
+        }
+    }
+    result
+}
+
+fn xyy_to_rgb(x: f64, y: f64, luminance: f64) -> Color {
+    if y <= 0.0 {
+        return Color::default()
+    }
+    let cx: f64 = x / y * luminance;
+    let cz: f64 = (1.0 - x - y) / y * luminance;
+    Color::new(
+        (3.2406 * cx - 1.5372 * luminance - 0.4986 * cz).max(0.0),
+        (-0.9689 * cx + 1.8758 * luminance + 0.0415 * cz).max(0.0),
+        (0.0557 * cx - 0.2040 * luminance + 1.0570 * cz).max(0.0),
+    )
+}
+
+// Fraction of sunlight reaching the ground at red, green and blue wavelengths, from Rayleigh and
+// aerosol scattering along the air mass the light crosses
+fn sun_transmittance(theta_sun: f64, turbidity: f64) -> Color {
+    let zenith_degrees: f64 = theta_sun.to_degrees().min(93.0);
+    let air_mass: f64 = 1.0 / (theta_sun.cos().max(0.0) + 0.15 * (93.885 - zenith_degrees).powf(-1.253));
+    let beta: f64 = 0.04608 * turbidity - 0.04586;
+    let mut result = Color::default();
+    // Wavelengths in micrometres
+    for (c, wavelength) in (0..3).zip([0.68_f64, 0.55, 0.44]) {
+        let rayleigh: f64 = 0.008735 * wavelength.powf(-4.08);
+        let aerosol: f64 = beta * wavelength.powf(-1.3);
+        result[c] = (-air_mass * (rayleigh + aerosol)).exp();
+    }
+    result
+}
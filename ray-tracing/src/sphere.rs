@@ -1,9 +1,11 @@
 use crate::aabb::AABB;
+use crate::aov::next_object_id;
 use crate::hittable::{HitRecord, Hittable};
 use crate::interval::Interval;
 use crate::material::Material;
+use crate::onb::ONB;
 use crate::ray::Ray;
-use crate::utilities::PI;
+use crate::utilities::{INFINITY, PI};
 use crate::vec3::{dot, Point3, Vec3};
 
 #[derive(Clone)]
@@ -11,7 +13,8 @@ pub struct Sphere {
     center: Ray,
     radius: f64,
     material: Box<dyn Material>,
-    bbox: AABB
+    bbox: AABB,
+    id: u32,
 }
 
 impl Sphere {
@@ -30,10 +33,16 @@ impl Sphere {
             radius: rad,
             material,
             bbox: AABB::new_from_children(&box1, &box2),
+            id: next_object_id(),
         }
     }
 
-    pub(crate) fn get_sphere_uv(&self, p: &Point3, u: &mut f64, v: &mut f64) -> () {
+    // A moving sphere goes from `center` at time 0 to `center2` at time 1 and stays there, within its bounding box
+    fn center_at(&self, time: f64) -> Point3 {
+        self.center.at(time.clamp(0.0, 1.0))
+    }
+
+    pub(crate) fn get_sphere_uv(&self, p: &Point3, u: &mut f64, v: &mut f64) {
         let theta: f64 = (-p.y()).acos();
         let phi: f64 = (-p.z()).atan2(p.x()) + PI;
         *u = phi / (2.0 * PI);
@@ -43,7 +52,7 @@ impl Sphere {
 
 impl Hittable for Sphere {
     fn hit(&self, ray: &Ray, interval: &Interval, record: &mut HitRecord) -> bool {
-        let current_center: Point3 = self.center.at(ray.time());
+        let current_center: Point3 = self.center_at(ray.time());
         let origin_center: Vec3 = current_center - *ray.origin();
         let a: f64 = ray.direction().length_squared();
         let h: f64 = dot(ray.direction(), &origin_center);
@@ -69,6 +78,7 @@ impl Hittable for Sphere {
         record.set_face_normal(ray, &outward_normal);
         self.get_sphere_uv(&outward_normal, &mut record.u, &mut record.v);
         record.material = self.material.clone();
+        record.object_id = self.id;
 
         true
     }
@@ -76,4 +86,35 @@ impl Hittable for Sphere {
     fn bounding_box(&self) -> AABB {
         self.bbox
     }
+
+    // Uniform over the cone of directions the sphere covers, seen from outside it
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        let mut record = HitRecord::default();
+        if !self.hit(&Ray::new(origin, direction, Some(time)), &Interval::new(0.001, INFINITY), &mut record) {
+            return 0.0
+        }
+        let distance_squared: f64 = (self.center_at(time) - *origin).length_squared();
+        if distance_squared <= self.radius * self.radius {
+            return 0.0
+        }
+        let cos_theta_max: f64 = (1.0 - self.radius * self.radius / distance_squared).sqrt();
+        let solid_angle: f64 = 2.0 * PI * (1.0 - cos_theta_max);
+        1.0 / solid_angle
+    }
+
+    fn sample_direction(&self, origin: &Point3, time: f64, u: f64, v: f64) -> Vec3 {
+        let direction: Vec3 = self.center_at(time) - *origin;
+        let distance_squared: f64 = direction.length_squared();
+        let uvw = ONB::new(&direction);
+        uvw.transform(&sample_to_sphere(self.radius, distance_squared, u, v))
+    }
+}
+
+// Direction within the cone towards a sphere along +z, `distance_squared` away from its centre
+fn sample_to_sphere(radius: f64, distance_squared: f64, u: f64, v: f64) -> Vec3 {
+    let cos_theta_max: f64 = (1.0 - radius * radius / distance_squared).max(0.0).sqrt();
+    let z: f64 = 1.0 + v * (cos_theta_max - 1.0);
+    let phi: f64 = 2.0 * PI * u;
+    let r: f64 = (1.0 - z * z).max(0.0).sqrt();
+    Vec3::new(phi.cos() * r, phi.sin() * r, z)
 }
\ No newline at end of file
@@ -4,7 +4,7 @@ use crate::perlin::Perlin;
 use crate::rtw_image::RTWImage;
 use crate::vec3::Point3;
 
-pub trait Texture: TextureClone {
+pub trait Texture: TextureClone + Send + Sync {
     fn value(&self, u: f64, v: f64, p: &Point3) -> Color;
 }
 
@@ -1,21 +1,45 @@
-use rand::Rng;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::cell::RefCell;
 
 use crate::interval::Interval;
 
 pub const INFINITY: f64 = f64::INFINITY;
-pub const PI: f64 = 3.1415926535897932385;
+pub const PI: f64 = std::f64::consts::PI;
+
+thread_local! {
+    // Every thread draws from its own stream, reseeded by the camera for each pixel sample
+    static RNG: RefCell<SmallRng> = RefCell::new(SmallRng::from_os_rng());
+}
 
 pub fn degrees_to_radians(degrees: f64) -> f64 {
     degrees * PI / 180.0
 }
 
+pub fn seed_random(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = SmallRng::seed_from_u64(seed));
+}
+
+// SplitMix64 finalizer, folds several values into one well-mixed seed
+pub fn hash_seed(values: &[u64]) -> u64 {
+    let mut hash: u64 = 0x9E37_79B9_7F4A_7C15;
+    for value in values {
+        hash ^= *value;
+        hash = hash.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        hash = (hash ^ (hash >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        hash = (hash ^ (hash >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        hash ^= hash >> 31;
+    }
+    hash
+}
+
 pub fn random_double(interval_opt: Option<Interval>) -> f64 {
     match interval_opt {
-        None => rand::rng().random_range(0.0..1.0),
+        None => RNG.with(|rng| rng.borrow_mut().random_range(0.0..1.0)),
         Some(interval) => interval.min + (interval.max - interval.min) * random_double(None),
     }
 }
 
 pub fn random_int(min: i32, max: i32) -> i32 {
     random_double(Some(Interval::new(min as f64, max as f64))) as i32
-}
\ No newline at end of file
+}
@@ -2,7 +2,7 @@ use std::fmt::{Display, Formatter, Result};
 use std::ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub};
 
 use crate::interval::Interval;
-use crate::utilities::random_double;
+use crate::utilities::{random_double, PI};
 
 #[derive(Clone, Copy, Default)]
 pub struct Vec3 {
@@ -228,12 +228,35 @@ pub fn random_in_unit_disk() -> Vec3 {
     }
 }
 
+// Maps a uniform 2D sample to a uniformly distributed direction
+pub fn sample_unit_vector(u: f64, v: f64) -> Vec3 {
+    let z: f64 = 1.0 - 2.0 * u;
+    let r: f64 = (1.0 - z * z).max(0.0).sqrt();
+    let phi: f64 = 2.0 * PI * v;
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+// Shirley-Chiu concentric mapping of a uniform 2D sample onto the unit disk
+pub fn sample_unit_disk(u: f64, v: f64) -> Vec3 {
+    let a: f64 = 2.0 * u - 1.0;
+    let b: f64 = 2.0 * v - 1.0;
+    if a == 0.0 && b == 0.0 {
+        return Vec3::default()
+    }
+    let (r, theta) = if a.abs() > b.abs() {
+        (a, (PI / 4.0) * (b / a))
+    } else {
+        (b, (PI / 2.0) - (PI / 4.0) * (a / b))
+    };
+    Vec3::new(r * theta.cos(), r * theta.sin(), 0.0)
+}
+
 pub fn random_on_hemisphere(normal: &Vec3) -> Vec3 {
     let on_unit_sphere: Vec3 = random_unit_vector();
     if dot(&on_unit_sphere, normal) > 0.0 {
-        return on_unit_sphere
+        on_unit_sphere
     } else {
-        return -on_unit_sphere
+        -on_unit_sphere
     }
 }
 